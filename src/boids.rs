@@ -1,22 +1,71 @@
-use alignment::{Alignment, AlignmentPlugin, AlignmentSet};
+use alignment::{Alignment, AlignmentPlugin};
+use std::f32::consts::{FRAC_PI_2, TAU};
+
 use bevy::{color::palettes::css::WHITE, ecs::query::QueryData};
 use bevy_inspector_egui::{quick::ResourceInspectorPlugin, InspectorOptions};
-use cohesion::{Cohesion, CohesionPlugin, CohesionSet};
-use configuration::{ConfigurationPlugin, ConfigurationSet, MaxForce, MaxSpeed, VisionRadius};
+use cohesion::{Cohesion, CohesionPlugin};
+use collision_avoidance::{
+    CollisionAvoidancePlugin, CollisionAvoidanceSet, UnalignedCollisionAvoidance,
+};
+use configuration::{
+    ConfigurationPlugin, ConfigurationSet, MaxForce, MaxSpeed, SimulationRng, VisionRadius,
+};
+use containment::{toroidal_world, Containment, ContainmentPlugin, ContainmentSet, WorldBounds};
+use energy::{Energy, EnergyPlugin};
+use flee::{Flee, FleePlugin, FleeSet};
+use flow_field::{FlowFieldPlugin, FollowFlowField, FollowFlowFieldSet};
+use hide::{Hide, HidePlugin, HideSet};
+use leader::{FollowLeader, FollowLeaderSet, LeaderPlugin};
+use lifecycle::{Age, BoidBorn, LifecyclePlugin};
 use obstacle_avoidance::{ObstacleAvoidance, ObstacleAvoidancePlugin, ObstacleAvoidanceSet};
 use obstacles::ObstaclesPlugin;
+use occlusion::OcclusionPlugin;
+use params::{BoidParams, ParamsPlugin};
+use path_following::{FollowPathSet, Path, PathFollowingPlugin};
+use perception::{NeighbourSearch, Neighbours, PerceptionPlugin, PerceptionSet};
+use predator::{EvadePredators, Predator, PredatorPlugin, PredatorSet};
+use pursuit::{PursuitPlugin, PursuitSet};
 use seek::{Seek, SeekPlugin, SeekSet};
-use separation::{Separation, SeparationPlugin, SeparationSet};
+use separation::{Separation, SeparationPlugin};
+use species::{FleeSpecies, Species, SpeciesPlugin, SpeciesRegistry};
+use steering::{
+    arbitrate_steering, reset_steering, SteeringBehaviour, SteeringContributions, SteeringForces,
+    SteeringPlugin,
+};
 use targets::TargetPlugin;
+use wander::{Wander, WanderPlugin, WanderSet};
 
 pub use configuration::SimulationConfig;
+pub use steering::SteeringTag;
 
 use crate::prelude::*;
 
+pub mod steering;
+
 pub mod seek;
 
+pub mod flee;
+
+pub mod pursuit;
+
+pub mod wander;
+
+pub mod path_following;
+
+pub mod leader;
+
+pub mod flow_field;
+
+pub mod containment;
+
+pub mod collision_avoidance;
+
+pub mod hide;
+
 pub mod separation;
 
+pub mod species;
+
 pub mod alignment;
 
 pub mod cohesion;
@@ -25,10 +74,24 @@ pub mod targets;
 
 pub mod configuration;
 
+pub mod falloff;
+
+pub mod energy;
+
+pub mod lifecycle;
+
 pub mod obstacle_avoidance;
 
 pub mod obstacles;
 
+pub mod params;
+
+pub mod predator;
+
+pub mod occlusion;
+
+pub mod perception;
+
 #[derive(QueryData)]
 #[query_data(mutable)]
 pub struct BoidsQuery {
@@ -36,7 +99,23 @@ pub struct BoidsQuery {
     pub vel: &'static mut LinearVelocity,
     pub transform: &'static Transform,
     pub dir: &'static mut SteeringDirection,
+    pub steering: &'static mut SteeringForces,
     pub entity: Entity,
+    pub params: Option<&'static BoidParams>,
+}
+
+impl BoidsQueryItem<'_> {
+    /// How fast the boid wants to go at most, its own params taken into account
+    pub fn max_speed(&self, config: &SimulationConfig) -> f32 {
+        BoidParams::max_speed_of(self.params, config)
+    }
+}
+
+impl BoidsQueryReadOnlyItem<'_> {
+    /// How fast the boid wants to go at most, its own params taken into account
+    pub fn max_speed(&self, config: &SimulationConfig) -> f32 {
+        BoidParams::max_speed_of(self.params, config)
+    }
 }
 
 #[derive(QueryData)]
@@ -47,8 +126,20 @@ pub struct BoidVisionQuery {
 }
 
 #[derive(Debug, Clone, Copy, Default)]
-pub struct BoidsPlugin;
+pub struct BoidsPlugin {
+    /// How the boids find their flockmates
+    pub neighbour_search: NeighbourSearch,
+}
+
+/// Clears last tick's steering
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResetSteeringSet;
 
+/// Every behaviour submits its steering force in here
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BehaviourSet;
+
+/// Combines the submitted forces and moves the boids
 #[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ServiceSet;
 
@@ -57,56 +148,113 @@ impl Plugin for BoidsPlugin {
         let config = SimulationConfig::default();
         app.insert_resource(config)
             // Additional simulation plugins
-            .add_plugins((TargetPlugin, ConfigurationPlugin, ObstaclesPlugin))
+            .add_plugins((
+                TargetPlugin,
+                ConfigurationPlugin,
+                ObstaclesPlugin,
+                OcclusionPlugin,
+                ParamsPlugin,
+                SpeciesPlugin,
+                EnergyPlugin,
+                LifecyclePlugin,
+                PerceptionPlugin {
+                    search: self.neighbour_search,
+                },
+                SteeringPlugin,
+            ))
             // Behaviour plugins
             .add_plugins((
                 SeekPlugin,
+                FleePlugin,
+                PursuitPlugin,
+                WanderPlugin,
+                PathFollowingPlugin,
+                LeaderPlugin,
+                FlowFieldPlugin,
+                ContainmentPlugin,
+                CollisionAvoidancePlugin,
+                HidePlugin,
+                PredatorPlugin,
                 SeparationPlugin,
                 AlignmentPlugin,
                 CohesionPlugin,
                 ObstacleAvoidancePlugin,
             ))
+            .add_systems(FixedUpdate, reset_steering.in_set(ResetSteeringSet))
             .add_systems(
                 FixedUpdate,
                 (
-                    rotate_boids,
+                    arbitrate_steering,
                     steer_boids,
-                    // screenwrap_boids,
-                    // reset_steer
+                    rotate_boids,
+                    screenwrap_boids.run_if(toroidal_world),
                 )
                     .chain()
                     .in_set(ServiceSet),
             )
             .add_systems(Update, (boids_gizmos,))
-            // Config -> Service & Seek -> Obstacle Avoidance -> Separation -> Cohesion -> Alignment
-            .configure_sets(FixedUpdate, ConfigurationSet.before(ServiceSet))
-            .configure_sets(FixedUpdate, SeekSet.before(SeparationSet))
-            .configure_sets(FixedUpdate, ObstacleAvoidanceSet.before(SeparationSet))
-            .configure_sets(FixedUpdate, SeparationSet.after(ServiceSet))
-            .configure_sets(FixedUpdate, CohesionSet.after(SeparationSet))
-            .configure_sets(FixedUpdate, AlignmentSet.after(CohesionSet))
+            // Config -> Reset -> Perception -> Behaviours -> Service
+            .configure_sets(
+                FixedUpdate,
+                (
+                    ConfigurationSet,
+                    ResetSteeringSet,
+                    PerceptionSet,
+                    BehaviourSet,
+                    ServiceSet,
+                )
+                    .chain(),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (
+                    SeekSet,
+                    FollowPathSet,
+                    FollowLeaderSet,
+                    FollowFlowFieldSet,
+                    FleeSet,
+                    HideSet,
+                    PursuitSet,
+                    PredatorSet,
+                    WanderSet,
+                    ContainmentSet,
+                    CollisionAvoidanceSet,
+                    ObstacleAvoidanceSet,
+                )
+                    .in_set(BehaviourSet),
+            )
+            // Wandering only kicks in once seeking has decided there's nothing to chase
+            .configure_sets(FixedUpdate, WanderSet.after(SeekSet))
+            // Predators pick their prey before giving chase
+            .configure_sets(FixedUpdate, PredatorSet.before(PursuitSet))
             .add_event::<SpawnBoid>()
             .add_observer(spawn_boid);
     }
 }
 
-#[derive(Component, Debug)]
-#[require(Collider)]
-pub struct BoidCollider;
-
 #[derive(Component, Debug)]
 #[require(Collider, Sensor, CollidingEntities)]
 pub struct BoidVisionCone;
 
-#[derive(Component, Debug)]
-pub struct SteeringDirection(Vec2);
+/// Whether `point` lies inside the cone of `fov` radians centered on the heading
+/// of a boid standing at `pos`
+pub fn in_field_of_view(pos: Vec2, heading: Vec2, point: Vec2, fov: f32) -> bool {
+    let Some(to_point) = (point - pos).try_normalize() else {
+        return true;
+    };
+    heading.angle_to(to_point).abs() <= fov / 2.
+}
 
-impl Default for SteeringDirection {
-    fn default() -> Self {
-        Self(Vec2::X)
-    }
+/// The direction the boid is facing, which is its velocity unless it's standing still
+pub fn boid_heading(transform: &Transform, vel: Vec2) -> Vec2 {
+    vel.try_normalize()
+        .unwrap_or_else(|| transform.up().truncate())
 }
 
+/// The steering force applied to the boid during the current tick
+#[derive(Component, Debug, Default)]
+pub struct SteeringDirection(Vec2);
+
 #[derive(Component)]
 #[require(
     Transform,
@@ -115,7 +263,11 @@ impl Default for SteeringDirection {
     Mesh2d,
     MeshMaterial2d<ColorMaterial>,
     RigidBody,
-    SteeringDirection
+    SteeringDirection,
+    SteeringForces,
+    SteeringContributions,
+    Neighbours,
+    Species
 )]
 pub struct Boid;
 
@@ -124,28 +276,48 @@ pub struct SpawnBoid {
     pub loc: Vec2,
     pub angle: f32,
     pub special: bool,
+    pub predator: bool,
+    pub species: Species,
+    /// The boid this one is the child of, if any
+    pub parent: Option<Entity>,
+    /// Overrides the params the boid would get from its species
+    pub params: Option<BoidParams>,
 }
 
 #[derive(Component)]
 pub struct SpecialBoid;
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_boid(
     trigger: Trigger<SpawnBoid>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     config: Res<SimulationConfig>,
+    neighbour_search: Res<NeighbourSearch>,
+    registry: Res<SpeciesRegistry>,
+    mut rng: ResMut<SimulationRng>,
 ) {
-    let scale = 10.;
+    let Some(species) = registry.get(trigger.species) else {
+        warn!(
+            "Unknown species {:?}, the boid won't be spawned",
+            trigger.species
+        );
+        return;
+    };
+    // Predators are bigger, so they're easy to tell apart
+    let scale = if trigger.predator { 16. } else { species.scale };
     let shape = Triangle2d::new(
         (0., scale).into(),
         (-scale / 2., -scale).into(),
         (scale / 2., -scale).into(),
     );
-    let color = if trigger.special {
+    let color = if trigger.predator {
+        Color::srgb_u8(128, 40, 160)
+    } else if trigger.special {
         Color::srgb(1., 0., 0.)
     } else {
-        Color::srgb_u8(2, 128, 144)
+        species.color
     };
     let mesh = meshes.add(shape);
     let material = materials.add(color);
@@ -153,34 +325,83 @@ pub fn spawn_boid(
         .mul_vec3(Vec3::X)
         .truncate();
 
-    let boid = commands
-        .spawn((
-            Boid,
-            Seek,
-            Separation,
-            Alignment,
-            Cohesion,
-            ObstacleAvoidance,
-            SteeringDirection(direction),
-            Transform::from_translation(trigger.loc.extend(0.)),
-            Mesh2d(mesh),
-            MeshMaterial2d(material),
-            Collider::triangle(
-                (0., scale).into(),
-                (-scale / 2., -scale).into(),
-                (scale / 2., -scale).into(),
+    let mut boid = commands.spawn((
+        Boid,
+        trigger.species,
+        Energy::new(config.energy_max),
+        // Not everyone lives as long, so that the flock doesn't die off all at once
+        Age::new(config.max_age * rng.rng().gen_range(0.8..1.2)),
+        LinearVelocity(direction * config.max_speed),
+        Transform::from_translation(trigger.loc.extend(0.)),
+        Mesh2d(mesh),
+        MeshMaterial2d(material),
+        Collider::triangle(
+            (0., scale).into(),
+            (-scale / 2., -scale).into(),
+            (scale / 2., -scale).into(),
+        ),
+        RigidBody::Kinematic,
+    ));
+    if trigger.predator {
+        boid.insert((
+            Predator,
+            Name::new("Predator"),
+            trigger
+                .params
+                .clone()
+                .unwrap_or_else(|| BoidParams::predator(&config)),
+            // Behaviours
+            (Wander::default(), Containment, ObstacleAvoidance),
+            // Predators touch the prey to catch it
+            CollidingEntities::default(),
+            CollisionLayers::new(
+                [GameCollisionLayer::Boids, GameCollisionLayer::Predators],
+                [
+                    GameCollisionLayer::VisionCones,
+                    GameCollisionLayer::Targets,
+                    GameCollisionLayer::Boids,
+                ],
+            ),
+        ));
+    } else {
+        if let Some(params) = trigger.params.clone().or_else(|| species.params.clone()) {
+            boid.insert(params);
+        }
+        boid.insert((
+            Name::new(species.name.clone()),
+            // Behaviours
+            (
+                Seek,
+                Flee,
+                Hide,
+                EvadePredators,
+                FleeSpecies,
+                Wander::default(),
+                FollowLeader,
+                FollowFlowField,
+                Containment,
+                UnalignedCollisionAvoidance,
+                Separation,
+                Alignment,
+                Cohesion,
+                ObstacleAvoidance,
             ),
-            RigidBody::Kinematic,
             CollisionLayers::new(
                 GameCollisionLayer::Boids,
-                [GameCollisionLayer::VisionCones, GameCollisionLayer::Targets],
+                [
+                    GameCollisionLayer::VisionCones,
+                    GameCollisionLayer::Targets,
+                    GameCollisionLayer::Predators,
+                ],
             ),
-        ))
-        .with_children(|parent| {
+        ));
+    }
+    if *neighbour_search == NeighbourSearch::Sensors {
+        boid.with_children(|parent| {
             parent.spawn((
                 Name::new("Vision Cone"),
                 BoidVisionCone,
-                Collider::circle(config.vision_radius),
+                Collider::circle(config.vision_distance()),
                 CollidingEntities::default(),
                 CollisionLayers::new(
                     GameCollisionLayer::VisionCones,
@@ -192,26 +413,102 @@ pub fn spawn_boid(
                 ),
                 Sensor,
             ));
-        })
-        .id();
+        });
+    }
+    let boid = boid.id();
 
     if trigger.special {
         commands
             .entity(boid)
             .insert((SpecialBoid, Name::new("Special Boid")));
     }
+    commands.trigger(BoidBorn {
+        boid,
+        parent: trigger.parent,
+    });
 }
 
 pub fn boids_gizmos(
-    q_special: Single<BoidsQuery, With<SpecialBoid>>,
+    q_special: Single<
+        (
+            BoidsQuery,
+            &SteeringContributions,
+            &Neighbours,
+            Option<&Wander>,
+        ),
+        With<SpecialBoid>,
+    >,
     q_boids: Query<BoidsQuery, Without<SpecialBoid>>,
-    q_vision_cones: Query<BoidVisionQuery>,
+    q_paths: Query<&Path>,
     vision_radius: Res<VisionRadius>,
     max_speed: Res<MaxSpeed>,
+    config: Res<SimulationConfig>,
     mut gizmos: Gizmos,
 ) {
+    let path_color = Color::srgba(1., 0.6, 0., 0.6);
+    for path in q_paths.iter() {
+        for (start, end) in path.segments() {
+            gizmos.line_2d(start, end, path_color);
+        }
+        for point in path.points.iter() {
+            gizmos.circle_2d(*point, path.radius, path_color.with_alpha(0.2));
+        }
+    }
+
+    let (q_special, contributions, neighbours, wander) = q_special.into_inner();
     let pos = q_special.transform.translation.truncate();
-    gizmos.circle_2d(pos, vision_radius.0, WHITE);
+    let heading = boid_heading(q_special.transform, q_special.vel.xy());
+    let fov = config.field_of_view.to_radians().min(TAU);
+    if fov < TAU {
+        // The arc starts from the local Y axis, so turn it back by half of the cone
+        let start = heading.to_angle() - FRAC_PI_2 - fov / 2.;
+        gizmos.arc_2d(
+            Isometry2d::new(pos, Rot2::radians(start)),
+            fov,
+            vision_radius.0,
+            WHITE,
+        );
+        for edge in [-fov / 2., fov / 2.] {
+            let edge_dir = Rot2::radians(edge) * heading;
+            gizmos.line_2d(pos, pos + edge_dir * vision_radius.0, WHITE);
+        }
+    } else {
+        gizmos.circle_2d(pos, vision_radius.0, WHITE);
+    }
+    // The reach of each flocking rule, inside of the outer perception bound
+    for (tag, radius) in [
+        (Separation::TAG, Separation::radius(&config)),
+        (Alignment::TAG, Alignment::radius(&config)),
+        (Cohesion::TAG, Cohesion::radius(&config)),
+    ] {
+        gizmos.circle_2d(
+            pos,
+            radius.min(vision_radius.0),
+            tag.color().with_alpha(0.4),
+        );
+    }
+
+    if let (Some(wander), Some(heading)) = (wander, q_special.vel.xy().try_normalize()) {
+        let wander_color = Color::srgba(0., 0.5, 1., 0.5);
+        let circle_center = pos + heading * config.wander_circle_distance;
+        gizmos.circle_2d(circle_center, config.wander_circle_radius, wander_color);
+        gizmos.line_2d(pos, pos + wander.target(heading, &config), wander_color);
+    }
+    // One arrow per behaviour, showing how much it actually weighed in
+    for contribution in contributions.0.iter() {
+        if contribution.applied.length_squared() < f32::EPSILON {
+            continue;
+        }
+        gizmos.arrow_2d(
+            pos,
+            pos + (contribution.applied * 10.).clamp_length_max(40.),
+            contribution.tag.color(),
+        );
+    }
+    // The ring takes the colour of the behaviour currently in charge
+    if let Some(dominant) = contributions.dominant() {
+        gizmos.circle_2d(pos, 15., dominant.tag.color());
+    }
     gizmos.arrow_2d(
         pos,
         pos + q_special.dir.0.clamp_length_max(30.),
@@ -223,51 +520,59 @@ pub fn boids_gizmos(
         Color::srgba(0., 1., 0., q_special.vel.xy().length() / max_speed.0),
     );
 
-    for vision_cone in q_vision_cones.iter() {
-        if vision_cone.parent.get() != q_special.entity {
-            continue;
-        }
-
-        for colliding_ent in vision_cone.colliding.iter() {
-            if let Ok(colliding_boid) = q_boids.get(*colliding_ent) {
-                let distance = (colliding_boid.transform.translation
-                    - q_special.transform.translation)
-                    .length();
-                let lines_color =
-                    Color::srgba(1., 0., 0., (vision_radius.0 - distance) / vision_radius.0);
-                gizmos.line_2d(
-                    pos,
-                    colliding_boid.transform.translation.truncate(),
-                    lines_color,
-                );
-            }
+    for neighbour in neighbours.iter() {
+        if let Ok(colliding_boid) = q_boids.get(*neighbour) {
+            let distance =
+                (colliding_boid.transform.translation - q_special.transform.translation).length();
+            let lines_color =
+                Color::srgba(1., 0., 0., (vision_radius.0 - distance) / vision_radius.0);
+            gizmos.line_2d(
+                pos,
+                colliding_boid.transform.translation.truncate(),
+                lines_color,
+            );
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn steer_boids(
     mut q_boids: Populated<(
         &SteeringDirection,
         &mut LinearVelocity,
         Option<&SpecialBoid>,
+        Option<&BoidParams>,
+        Option<&Energy>,
     )>,
     max_force: Res<MaxForce>,
     max_speed: Res<MaxSpeed>,
+    config: Res<SimulationConfig>,
 ) {
-    for (steer_direction, mut linear_velocity, special) in q_boids.iter_mut() {
-        let steer_force = steer_direction.0.clamp_length_max(max_force.0);
+    for (steer_direction, mut linear_velocity, special, params, energy) in q_boids.iter_mut() {
+        let (max_force, mut max_speed) = match params {
+            Some(params) => (params.max_force(&config), params.max_speed(&config)),
+            None => (max_force.0, max_speed.0),
+        };
+        // Starving boids can barely drag themselves around
+        if energy.is_some_and(Energy::is_starving) {
+            max_speed *= config.starving_speed_factor;
+        }
+        let steer_force = steer_direction.0.clamp_length_max(max_force);
         let acceleration = steer_force; // Consider mass = 1
         if special.is_some() {
-            // info!("Max force: {:?}, max speed: {:?}", max_force.0, max_speed.0);
+            // info!("Max force: {:?}, max speed: {:?}", max_force, max_speed);
         }
-        let new_velocity = (linear_velocity.xy() + acceleration).clamp_length_max(max_speed.0);
+        let new_velocity = (linear_velocity.xy() + acceleration).clamp_length_max(max_speed);
         *linear_velocity = LinearVelocity::from(new_velocity);
     }
 }
 
 pub fn rotate_boids(mut q_boids: Populated<(&LinearVelocity, &mut Transform), With<Boid>>) {
     for (an_vel, mut transform) in q_boids.iter_mut() {
-        let new_forward = an_vel.xy().normalize();
+        // A parked boid keeps its last heading
+        let Some(new_forward) = an_vel.xy().try_normalize() else {
+            continue;
+        };
         let new_rot = Quat::from_rotation_z(new_forward.to_angle() - std::f32::consts::FRAC_PI_2);
         transform.rotation = new_rot
     }
@@ -275,14 +580,12 @@ pub fn rotate_boids(mut q_boids: Populated<(&LinearVelocity, &mut Transform), Wi
 
 pub fn screenwrap_boids(
     mut q_boids: Populated<&mut Transform, With<Boid>>,
-    q_window: Populated<&Window>,
+    bounds: Res<WorldBounds>,
 ) {
-    let window = q_window.single();
-    let window_size = window.size();
-    let world_halfwidth = window_size.x / 2.;
-    let world_halfheight = window_size.y / 2.;
+    let world_halfwidth = bounds.half_size.x;
+    let world_halfheight = bounds.half_size.y;
     let make_wrap = move |val: f32| {
-        let res = move |num| {
+        move |num| {
             if num > val {
                 -val
             } else if num < -val {
@@ -290,8 +593,7 @@ pub fn screenwrap_boids(
             } else {
                 num
             }
-        };
-        res
+        }
     };
     let wrapx = make_wrap(world_halfwidth);
     let wrapy = make_wrap(world_halfheight);
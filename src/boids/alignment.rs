@@ -1,51 +1,51 @@
 use super::*;
+use steering::{BoidState, SteeringBehaviour, SteeringBehaviourAppExt};
 
 pub struct AlignmentPlugin;
 
-#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct AlignmentSet;
-
 impl Plugin for AlignmentPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, alignment_behaviour.in_set(AlignmentSet));
+        app.add_steering_behaviour::<Alignment>();
     }
 }
 
 #[derive(Component)]
 pub struct Alignment;
 
-fn alignment_behaviour(
-    mut q_boids: Populated<BoidsQuery, With<Alignment>>,
-    q_vision_cones: Query<BoidVisionQuery>,
-    config: Res<SimulationConfig>,
-) {
-    for vision_cone in q_vision_cones.iter() {
-        if vision_cone.colliding.is_empty() {
-            continue;
-        }
+impl SteeringBehaviour for Alignment {
+    const TAG: SteeringTag = SteeringTag("Alignment");
+    const PRIORITY: i32 = 20;
 
-        let seen_boids = vision_cone
-            .colliding
-            .iter()
-            .filter_map(|ent| q_boids.get(*ent).ok())
-            .collect::<Vec<_>>();
-
-        if seen_boids.is_empty() {
-            continue;
-        }
+    fn weight(config: &SimulationConfig) -> f32 {
+        config.alignment_strength
+    }
 
-        let parent_ent = vision_cone.parent.get();
-        let boid = q_boids.get(parent_ent).expect("The boid should be present");
+    fn radius(config: &SimulationConfig) -> f32 {
+        config.vision_distance() * config.alignment_radius_rel
+    }
 
-        let avg_vel = seen_boids
+    fn steer(
+        boid: &BoidState,
+        neighbours: &[BoidState],
+        config: &SimulationConfig,
+    ) -> Option<Vec2> {
+        let radius = Self::radius(config);
+        let (weighted_sum, total_weight) = neighbours
             .iter()
-            .map(|other| other.vel.xy())
-            .reduce(|acc, e| acc + e)
-            .expect("Should get a sum velocity")
-            .div_euclid(Vec2::splat(seen_boids.len() as f32));
-
-        let accel_dir = avg_vel - boid.vel.xy();
-        let mut boid = q_boids.get_mut(parent_ent).expect("Should get boid");
-        boid.dir.0 += accel_dir.normalize() * config.alignment_strength;
+            .map(|other| {
+                let weight = config
+                    .alignment_falloff
+                    .weight(boid.pos.distance(other.pos), radius);
+                (other.vel * weight, weight)
+            })
+            .fold((Vec2::ZERO, 0.), |(sum, total), (value, weight)| {
+                (sum + value, total + weight)
+            });
+        if total_weight <= f32::EPSILON {
+            return None;
+        }
+        let avg_vel = weighted_sum / total_weight;
+
+        (avg_vel - boid.vel).try_normalize()
     }
 }
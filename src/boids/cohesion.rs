@@ -1,47 +1,51 @@
 use super::*;
+use steering::{BoidState, SteeringBehaviour, SteeringBehaviourAppExt};
 
 pub struct CohesionPlugin;
 
-#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct CohesionSet;
-
 impl Plugin for CohesionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, cohesion_behaviour.in_set(CohesionSet));
+        app.add_steering_behaviour::<Cohesion>();
     }
 }
 
 #[derive(Component)]
 pub struct Cohesion;
 
-fn cohesion_behaviour(
-    mut q_boids: Populated<BoidsQuery, With<Cohesion>>,
-    q_vision_cones: Query<BoidVisionQuery>,
-    config: Res<SimulationConfig>,
-) {
-    for vision_cone in q_vision_cones.iter() {
-        let seen_boids = vision_cone
-            .colliding
-            .iter()
-            .filter_map(|ent| q_boids.get(*ent).ok())
-            .collect::<Vec<_>>();
+impl SteeringBehaviour for Cohesion {
+    const TAG: SteeringTag = SteeringTag("Cohesion");
+    const PRIORITY: i32 = 20;
 
-        if seen_boids.is_empty() {
-            continue;
-        }
+    fn weight(config: &SimulationConfig) -> f32 {
+        config.cohesion_strength
+    }
 
-        let parent_ent = vision_cone.parent.get();
-        let boid = q_boids.get(parent_ent).expect("The boid should be present");
+    fn radius(config: &SimulationConfig) -> f32 {
+        config.vision_distance() * config.cohesion_radius_rel
+    }
 
-        let avg_pos = seen_boids
+    fn steer(
+        boid: &BoidState,
+        neighbours: &[BoidState],
+        config: &SimulationConfig,
+    ) -> Option<Vec2> {
+        let radius = Self::radius(config);
+        let (weighted_sum, total_weight) = neighbours
             .iter()
-            .map(|other| other.transform.translation.truncate())
-            .reduce(|acc, e| acc + e)
-            .expect("Should get a sum velocity")
-            .div_euclid(Vec2::splat(seen_boids.len() as f32));
-
-        let accel_dir = avg_pos - boid.transform.translation.xy();
-        let mut boid = q_boids.get_mut(parent_ent).expect("Should get boid");
-        boid.dir.0 += accel_dir.normalize() * config.cohesion_strength;
+            .map(|other| {
+                let weight = config
+                    .cohesion_falloff
+                    .weight(boid.pos.distance(other.pos), radius);
+                (other.pos * weight, weight)
+            })
+            .fold((Vec2::ZERO, 0.), |(sum, total), (value, weight)| {
+                (sum + value, total + weight)
+            });
+        if total_weight <= f32::EPSILON {
+            return None;
+        }
+        let avg_pos = weighted_sum / total_weight;
+
+        (avg_pos - boid.pos).try_normalize()
     }
 }
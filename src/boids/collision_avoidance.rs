@@ -0,0 +1,119 @@
+use super::*;
+use perception::Neighbours;
+
+pub struct CollisionAvoidancePlugin;
+
+impl Plugin for CollisionAvoidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            collision_avoidance_behaviour.in_set(CollisionAvoidanceSet),
+        );
+    }
+}
+
+/// Makes the boid dodge the flockmates it is about to run into, judging by
+/// where both of them will be rather than where they are
+#[derive(Component)]
+pub struct UnalignedCollisionAvoidance;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CollisionAvoidanceSet;
+
+const TAG: SteeringTag = SteeringTag("UnalignedCollisionAvoidance");
+const PRIORITY: i32 = 80;
+
+/// The moment two boids moving in straight lines are the closest to each other
+struct ClosestApproach {
+    time: f32,
+    /// Own position at that moment
+    own_pos: Vec2,
+    /// The other boid's position at that moment
+    other_pos: Vec2,
+}
+
+fn closest_approach(
+    pos: Vec2,
+    vel: Vec2,
+    other_pos: Vec2,
+    other_vel: Vec2,
+) -> Option<ClosestApproach> {
+    let relative_pos = other_pos - pos;
+    let relative_vel = other_vel - vel;
+    let relative_speed_sq = relative_vel.length_squared();
+    // Moving in lockstep, the distance never changes
+    if relative_speed_sq < f32::EPSILON {
+        return None;
+    }
+
+    let time = -relative_pos.dot(relative_vel) / relative_speed_sq;
+    Some(ClosestApproach {
+        time,
+        own_pos: pos + vel * time,
+        other_pos: other_pos + other_vel * time,
+    })
+}
+
+fn collision_avoidance_behaviour(
+    mut q_boids: Populated<(BoidsQuery, &Neighbours), With<UnalignedCollisionAvoidance>>,
+    config: Res<SimulationConfig>,
+) {
+    let max_count = config.neighbour_mode.max_count(&config);
+    let mut steers = Vec::new();
+    for (boid, neighbours) in q_boids.iter() {
+        let pos = boid.transform.translation.truncate();
+        let vel = boid.vel.xy();
+        let Some(forward) = vel.try_normalize() else {
+            continue;
+        };
+
+        // Only the most imminent collision is worth reacting to
+        let threat = neighbours
+            .iter()
+            .filter_map(|ent| q_boids.get(*ent).ok())
+            .take(max_count)
+            .filter_map(|(other, _)| {
+                let approach = closest_approach(
+                    pos,
+                    vel,
+                    other.transform.translation.truncate(),
+                    other.vel.xy(),
+                )?;
+                let too_close = approach.own_pos.distance(approach.other_pos)
+                    < config.collision_avoidance_distance;
+                let upcoming = (0.0..config.collision_prediction_time).contains(&approach.time);
+                (too_close && upcoming).then_some((approach, other.vel.xy()))
+            })
+            .min_by(|(a, _), (b, _)| a.time.total_cmp(&b.time));
+
+        let Some((approach, other_vel)) = threat else {
+            continue;
+        };
+
+        // Sidestep the spot where the other boid is going to be
+        let offset = approach.own_pos - approach.other_pos;
+        let lateral = (offset - forward * offset.dot(forward))
+            .try_normalize()
+            .unwrap_or(forward.perp());
+
+        // Unless meeting head on, also let the other boid pass by braking when it's
+        // going to be ahead of us, or speeding up when it's going to be behind
+        let head_on = forward.dot(other_vel.normalize_or_zero()) < -0.7;
+        let pace = if head_on {
+            Vec2::ZERO
+        } else if offset.dot(forward) < 0. {
+            -forward * 0.5
+        } else {
+            forward * 0.5
+        };
+
+        let urgency = 1. - approach.time / config.collision_prediction_time;
+        steers.push((boid.entity, (lateral + pace).normalize() * urgency));
+    }
+
+    for (ent, steer) in steers {
+        let (mut boid, _) = q_boids.get_mut(ent).expect("Should get boid");
+        boid.steering
+            .submit(TAG, steer * config.collision_avoidance_strength, PRIORITY);
+    }
+}
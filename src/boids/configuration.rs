@@ -1,4 +1,9 @@
+use rand::{rngs::StdRng, SeedableRng};
+
 use super::*;
+use falloff::Falloff;
+use perception::NeighbourMode;
+use steering::CombinationStrategy;
 
 pub struct ConfigurationPlugin;
 
@@ -11,11 +16,17 @@ impl Plugin for ConfigurationPlugin {
             .insert_resource(VisionRadius::default())
             .insert_resource(MaxSpeed::default())
             .insert_resource(MaxForce::default())
+            .insert_resource(SimulationRng::new(SimulationConfig::default().rng_seed))
             .register_type::<SimulationConfig>()
             .add_plugins(ResourceInspectorPlugin::<SimulationConfig>::default())
             .add_systems(
                 FixedUpdate,
-                (update_max_speed, update_max_force, update_vision_radius)
+                (
+                    update_max_speed,
+                    update_max_force,
+                    update_vision_radius,
+                    update_rng_seed,
+                )
                     .run_if(resource_changed::<SimulationConfig>)
                     .in_set(ConfigurationSet),
             )
@@ -37,14 +48,155 @@ pub struct SimulationConfig {
     pub max_speed: f32,
     /// The radius, at which the boid sees its flockmates
     pub vision_radius: f32,
+    /// The angle, in degrees, of the cone in front of the boid in which it notices
+    /// its flockmates. Anything below 360 leaves a blind spot behind it
+    pub field_of_view: f32,
+    /// Whether the neighbourhood is made of everyone within the vision radius,
+    /// or only of the closest few flockmates
+    pub neighbour_mode: NeighbourMode,
+    /// How many flockmates a boid keeps track of in the k-nearest modes
+    pub neighbour_count: usize,
+    /// Whether obstacles block the view between flockmates
+    pub line_of_sight_occlusion: bool,
+    /// The most rays cast every tick to check whether flockmates can see each other
+    pub line_of_sight_rays_per_tick: usize,
+    /// How many ticks the outcome of a line of sight check is trusted for
+    pub line_of_sight_cache_ticks: u32,
     /// The weight of the separation instinct
     pub separation_strength: f32,
+    /// The distance under which flockmates get pushed away, expressed as a
+    /// fraction of the vision radius
+    pub separation_radius_rel: f32,
+    /// How the push away from a flockmate fades with its distance
+    pub separation_falloff: Falloff,
     /// The weight of the cohesion instinct
     pub cohesion_strength: f32,
+    /// The distance under which flockmates draw the boid towards the center
+    /// of the group, expressed as a fraction of the vision radius
+    pub cohesion_radius_rel: f32,
+    /// How the pull of a flockmate towards the center of the group fades with its distance
+    pub cohesion_falloff: Falloff,
     /// The weight of the alignment instinct
     pub alignment_strength: f32,
+    /// The distance under which flockmates have a say in the common heading,
+    /// expressed as a fraction of the vision radius
+    pub alignment_radius_rel: f32,
+    /// How the say of a flockmate in the common heading fades with its distance
+    pub alignment_falloff: Falloff,
     /// The weight of the seek instinct
     pub seek_strength: f32,
+    /// The distance to a seek target, under which arriving boids start slowing down
+    pub arrive_slowing_radius: f32,
+    /// The weight of the flee instinct
+    pub flee_strength: f32,
+    /// The distance to a flee target, under which the boid starts running away
+    pub flee_panic_radius: f32,
+    /// The weight of the pursuit instinct
+    pub pursuit_strength: f32,
+    /// The weight of the evasion instinct
+    pub evade_strength: f32,
+    /// The furthest into the future, in seconds, a boid tries to predict the
+    /// position of the entity it pursues or evades
+    pub prediction_horizon: f32,
+    /// The distance ahead of the boid, at which the wander circle is placed
+    pub wander_circle_distance: f32,
+    /// The radius of the wander circle
+    pub wander_circle_radius: f32,
+    /// How fast, in radians per second, the wander target can drift along the circle
+    pub wander_jitter: f32,
+    /// The weight of the wander instinct
+    pub wander_strength: f32,
+    /// How far ahead along its velocity the boid checks whether it's leaving the path
+    pub path_prediction_distance: f32,
+    /// How far ahead along the path the boid aims when getting back on track
+    pub path_lookahead: f32,
+    /// The weight of the path following instinct
+    pub path_following_strength: f32,
+    /// How far behind the leader its followers try to stay
+    pub leader_behind_distance: f32,
+    /// The distance to the leader or the spot in front of it, under which a follower
+    /// considers itself in the leader's way
+    pub leader_sight_radius: f32,
+    /// The weight of the leader following instinct
+    pub leader_following_strength: f32,
+    /// How far ahead along its velocity the boid samples the flow field
+    pub flow_field_lookahead: f32,
+    /// The weight of the flow field following instinct
+    pub flow_field_strength: f32,
+    /// The weight of the instinct to stay within the world bounds
+    pub containment_strength: f32,
+    /// How far into the future, in seconds, the boid looks for collisions with its flockmates
+    pub collision_prediction_time: f32,
+    /// The distance between two boids at their closest approach, under which
+    /// they are considered colliding
+    pub collision_avoidance_distance: f32,
+    /// The weight of the collision avoidance instinct
+    pub collision_avoidance_strength: f32,
+    /// How far from the boid obstacles are still considered as cover
+    pub hide_search_radius: f32,
+    /// How far behind the obstacle the boid hides
+    pub hide_distance: f32,
+    /// The weight of the hiding instinct
+    pub hide_strength: f32,
+    /// The maximum speed of the predators
+    pub predator_max_speed: f32,
+    /// The maximum force that can be applied to a predator, as a fraction of its
+    /// maximum speed
+    pub predator_max_force: f32,
+    /// How far the predators spot their prey from
+    pub predator_sight_radius: f32,
+    /// Whether caught prey comes back somewhere else in the world, or is gone for good
+    pub respawn_caught_prey: bool,
+    /// The energy of a well-fed boid
+    pub energy_max: f32,
+    /// The energy burnt every second just by being alive
+    pub energy_idle_burn: f32,
+    /// The energy burnt every second when going at full speed
+    pub energy_speed_burn: f32,
+    /// The energy burnt every second when steering with full force
+    pub energy_steering_burn: f32,
+    /// The fraction of its maximum speed a boid with no energy left can still reach
+    pub starving_speed_factor: f32,
+    /// How long, in seconds, a boid survives with no energy left
+    pub starvation_time: f32,
+    /// The energy a boid gets out of food every second while touching it
+    pub food_eating_rate: f32,
+    /// The time, in seconds, between two drops of food
+    pub food_spawn_interval: f32,
+    /// The amount of food the world holds at most
+    pub max_food_sources: usize,
+    /// How far the boids smell food from
+    pub food_sight_radius: f32,
+    /// The hunger, from 0 to 1, past which boids start looking for food
+    pub hunger_threshold: f32,
+    /// How much stronger the seeking instinct gets as the boid starves, on top
+    /// of the seek strength
+    pub hunger_seek_boost: f32,
+    /// The energy a predator gets out of a caught prey
+    pub predator_meal_energy: f32,
+    /// The average age, in seconds, at which boids die of old age
+    pub max_age: f32,
+    /// The age, in seconds, from which boids can have offspring
+    pub maturity_age: f32,
+    /// The time, in seconds, a boid waits between two children
+    pub reproduction_interval: f32,
+    /// The fraction of its maximum energy a boid needs to have a child
+    pub reproduction_energy_threshold: f32,
+    /// The energy a boid spends on having a child
+    pub reproduction_energy_cost: f32,
+    /// The chance of each inherited parameter being mutated
+    pub mutation_rate: f32,
+    /// How much a mutation changes a parameter, as a fraction of its value
+    pub mutation_strength: f32,
+    /// The number of boids past which no more children are born
+    pub max_population: usize,
+    /// How the forces of the different behaviours are combined, unless the boid
+    /// has its own arbitration settings
+    pub steering_strategy: CombinationStrategy,
+    /// The chance of a behaviour getting picked under prioritized dithering
+    pub dithering_probability: f32,
+    /// The seed of the random generator driving the stochastic behaviours
+    pub rng_seed: u64,
     /// The number of rays that will be uniformly cast on _one side_ during
     /// the obstacle detection process
     pub obstacle_detection_density: i32,
@@ -58,13 +210,78 @@ pub struct SimulationConfig {
 impl Default for SimulationConfig {
     fn default() -> Self {
         Self {
-            max_force: 0.75,
+            max_force: 0.02,
             max_speed: 150.,
             vision_radius: 1.5,
+            field_of_view: 270.,
+            neighbour_mode: NeighbourMode::default(),
+            neighbour_count: 7,
+            line_of_sight_occlusion: true,
+            line_of_sight_rays_per_tick: 400,
+            line_of_sight_cache_ticks: 10,
             separation_strength: 1.05,
+            separation_radius_rel: 0.3,
+            separation_falloff: Falloff::Inverse,
             cohesion_strength: 1.,
+            cohesion_radius_rel: 1.,
+            cohesion_falloff: Falloff::Constant,
             alignment_strength: 0.2,
+            alignment_radius_rel: 0.7,
+            alignment_falloff: Falloff::Constant,
             seek_strength: 0.1,
+            arrive_slowing_radius: 200.,
+            flee_strength: 2.,
+            flee_panic_radius: 150.,
+            pursuit_strength: 1.5,
+            evade_strength: 2.,
+            prediction_horizon: 1.,
+            wander_circle_distance: 60.,
+            wander_circle_radius: 30.,
+            wander_jitter: 6.,
+            wander_strength: 0.3,
+            path_prediction_distance: 25.,
+            path_lookahead: 40.,
+            path_following_strength: 1.,
+            leader_behind_distance: 50.,
+            leader_sight_radius: 40.,
+            leader_following_strength: 1.5,
+            flow_field_lookahead: 25.,
+            flow_field_strength: 1.,
+            containment_strength: 3.,
+            collision_prediction_time: 1.5,
+            collision_avoidance_distance: 20.,
+            collision_avoidance_strength: 2.,
+            hide_search_radius: 400.,
+            hide_distance: 30.,
+            hide_strength: 2.,
+            predator_max_speed: 170.,
+            predator_max_force: 0.015,
+            predator_sight_radius: 500.,
+            respawn_caught_prey: true,
+            energy_max: 100.,
+            energy_idle_burn: 0.5,
+            energy_speed_burn: 1.5,
+            energy_steering_burn: 1.,
+            starving_speed_factor: 0.4,
+            starvation_time: 10.,
+            food_eating_rate: 40.,
+            food_spawn_interval: 1.,
+            max_food_sources: 20,
+            food_sight_radius: 400.,
+            hunger_threshold: 0.3,
+            hunger_seek_boost: 10.,
+            predator_meal_energy: 60.,
+            max_age: 300.,
+            maturity_age: 30.,
+            reproduction_interval: 20.,
+            reproduction_energy_threshold: 0.8,
+            reproduction_energy_cost: 40.,
+            mutation_rate: 0.1,
+            mutation_strength: 0.1,
+            max_population: 400,
+            steering_strategy: CombinationStrategy::default(),
+            dithering_probability: 0.5,
+            rng_seed: 0,
             obstacle_detection_radius_rel: 0.5,
             obstacle_detection_density: 10,
             obstacle_avoidance_strength: 2.,
@@ -72,6 +289,13 @@ impl Default for SimulationConfig {
     }
 }
 
+impl SimulationConfig {
+    /// The distance at which the boid sees its flockmates
+    pub fn vision_distance(&self) -> f32 {
+        self.max_speed * self.vision_radius
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct VisionRadius(pub f32);
 
@@ -81,6 +305,27 @@ pub struct MaxSpeed(pub f32);
 #[derive(Resource, Default)]
 pub struct MaxForce(pub f32);
 
+/// The random generator shared by all the stochastic behaviours, so that a run
+/// can be reproduced from its seed
+#[derive(Resource)]
+pub struct SimulationRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
 fn update_max_speed(config: Res<SimulationConfig>, mut max_speed: ResMut<MaxSpeed>) {
     max_speed.0 = config.max_speed;
 }
@@ -90,7 +335,13 @@ fn update_max_force(config: Res<SimulationConfig>, mut max_force: ResMut<MaxForc
 }
 
 fn update_vision_radius(config: Res<SimulationConfig>, mut vision_radius: ResMut<VisionRadius>) {
-    vision_radius.0 = config.max_speed * config.vision_radius;
+    vision_radius.0 = config.vision_distance();
+}
+
+fn update_rng_seed(config: Res<SimulationConfig>, mut rng: ResMut<SimulationRng>) {
+    if rng.seed != config.rng_seed {
+        *rng = SimulationRng::new(config.rng_seed);
+    }
 }
 
 fn update_vision_colliders(
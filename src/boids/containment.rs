@@ -0,0 +1,146 @@
+use super::*;
+
+pub struct ContainmentPlugin;
+
+impl Plugin for ContainmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldBounds::default())
+            .register_type::<WorldBounds>()
+            .add_plugins(ResourceInspectorPlugin::<WorldBounds>::default())
+            .add_systems(
+                FixedUpdate,
+                containment_behaviour
+                    .run_if(not(toroidal_world))
+                    .in_set(ContainmentSet),
+            )
+            .add_systems(Update, world_bounds_gizmos);
+    }
+}
+
+/// Makes the boid turn back before it leaves the [`WorldBounds`]
+#[derive(Component)]
+pub struct Containment;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContainmentSet;
+
+const TAG: SteeringTag = SteeringTag("Containment");
+const PRIORITY: i32 = 90;
+
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainmentMode {
+    /// Boids turn back before reaching the edges of a rectangle
+    #[default]
+    Rectangle,
+    /// Boids turn back before reaching the edge of a circle
+    Circle,
+    /// Boids leaving through one edge of the rectangle come back through the opposite one
+    Toroidal,
+}
+
+/// The part of the world the boids are kept in, centered around the origin
+#[derive(Reflect, Resource, InspectorOptions)]
+#[reflect(Resource)]
+pub struct WorldBounds {
+    pub mode: ContainmentMode,
+    /// Half of the width and height of the rectangular and toroidal worlds
+    pub half_size: Vec2,
+    /// The radius of the circular world
+    pub radius: f32,
+    /// The distance from the edge, at which the boids start turning back
+    pub margin: f32,
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        Self {
+            mode: ContainmentMode::default(),
+            half_size: Vec2::new(1200., 900.),
+            radius: 900.,
+            margin: 150.,
+        }
+    }
+}
+
+impl WorldBounds {
+    /// A random spot of the world, away from the margin
+    pub fn random_point(&self, rng: &mut impl Rng) -> Vec2 {
+        match self.mode {
+            ContainmentMode::Circle => {
+                let inner = (self.radius - self.margin).max(0.);
+                let angle = rng.gen_range(0. ..std::f32::consts::TAU);
+                // The square root spreads the points evenly over the disc
+                Vec2::from_angle(angle) * inner * rng.gen::<f32>().sqrt()
+            }
+            ContainmentMode::Rectangle | ContainmentMode::Toroidal => {
+                let inner = (self.half_size - self.margin).max(Vec2::ONE);
+                Vec2::new(
+                    rng.gen_range(-inner.x..inner.x),
+                    rng.gen_range(-inner.y..inner.y),
+                )
+            }
+        }
+    }
+
+    /// Half of the size of the box enclosing the world, whatever its shape
+    pub fn half_extents(&self) -> Vec2 {
+        match self.mode {
+            ContainmentMode::Circle => Vec2::splat(self.radius),
+            ContainmentMode::Rectangle | ContainmentMode::Toroidal => self.half_size,
+        }
+    }
+
+    /// How deep into the margin the position is, expressed as the direction back
+    /// to safety scaled by the fraction of the margin crossed
+    pub fn intrusion(&self, pos: Vec2) -> Option<Vec2> {
+        let intrusion = match self.mode {
+            ContainmentMode::Rectangle => {
+                let inner = (self.half_size - self.margin).max(Vec2::ZERO);
+                -(pos.abs() - inner).max(Vec2::ZERO) * pos.signum()
+            }
+            ContainmentMode::Circle => {
+                let depth = (pos.length() - (self.radius - self.margin)).max(0.);
+                -pos.normalize_or_zero() * depth
+            }
+            ContainmentMode::Toroidal => return None,
+        };
+
+        // A zero margin makes any intrusion a full one, rather than an infinite one
+        (intrusion != Vec2::ZERO).then(|| intrusion / self.margin.max(f32::EPSILON))
+    }
+}
+
+pub fn toroidal_world(bounds: Res<WorldBounds>) -> bool {
+    bounds.mode == ContainmentMode::Toroidal
+}
+
+fn containment_behaviour(
+    mut q_boids: Populated<BoidsQuery, With<Containment>>,
+    bounds: Res<WorldBounds>,
+    config: Res<SimulationConfig>,
+) {
+    for mut boid in q_boids.iter_mut() {
+        let Some(intrusion) = bounds.intrusion(boid.transform.translation.truncate()) else {
+            continue;
+        };
+
+        // The deeper the boid is into the margin, the harder it turns
+        let desired_vel = intrusion.normalize() * boid.max_speed(&config);
+        let urgency = intrusion.length().min(1.);
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero() * urgency;
+        boid.steering
+            .submit(TAG, steer * config.containment_strength, PRIORITY);
+    }
+}
+
+fn world_bounds_gizmos(bounds: Res<WorldBounds>, mut gizmos: Gizmos) {
+    let color = Color::srgb(0.3, 0.3, 0.3);
+    match bounds.mode {
+        ContainmentMode::Rectangle | ContainmentMode::Toroidal => {
+            gizmos.rect_2d(Vec2::ZERO, bounds.half_size * 2., color);
+        }
+        ContainmentMode::Circle => {
+            gizmos.circle_2d(Vec2::ZERO, bounds.radius, color);
+        }
+    }
+}
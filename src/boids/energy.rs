@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use configuration::SimulationRng;
+use lifecycle::{BoidDied, DeathCause};
+use predator::BoidCaught;
+use targets::{FoodSource, SpawnTarget};
+
+use super::*;
+
+pub struct EnergyPlugin;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Energy>()
+            .init_resource::<FoodSpawner>()
+            .add_systems(
+                FixedUpdate,
+                (burn_energy, starve_boids, eat_food, spawn_food)
+                    .chain()
+                    .in_set(ServiceSet)
+                    .after(steer_boids),
+            )
+            .add_systems(Update, energy_gizmos)
+            .add_observer(feed_predator);
+    }
+}
+
+/// What keeps a boid going. Moving and steering burn it, eating brings it back,
+/// and a boid running out of it slows down before dying of starvation
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct Energy {
+    pub current: f32,
+    pub max: f32,
+    /// How long, in seconds, the boid has had no energy left
+    starving_for: f32,
+}
+
+impl Energy {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            starving_for: 0.,
+        }
+    }
+
+    /// How empty the boid is, from 0 when full to 1 when out of energy
+    pub fn hunger(&self) -> f32 {
+        1. - (self.current / self.max).clamp(0., 1.)
+    }
+
+    pub fn is_starving(&self) -> bool {
+        self.current <= 0.
+    }
+
+    /// Adds `amount` of energy, up to the maximum, and returns how much was taken
+    pub fn gain(&mut self, amount: f32) -> f32 {
+        let taken = amount.min(self.max - self.current).max(0.);
+        self.current += taken;
+        if taken > 0. {
+            self.starving_for = 0.;
+        }
+        taken
+    }
+}
+
+/// Drops food at random spots of the world at a regular pace
+#[derive(Resource)]
+struct FoodSpawner(Timer);
+
+impl Default for FoodSpawner {
+    fn default() -> Self {
+        let interval = SimulationConfig::default().food_spawn_interval;
+        Self(Timer::from_seconds(interval, TimerMode::Repeating))
+    }
+}
+
+/// Going fast and steering hard both cost energy, on top of what the boid
+/// burns just by being alive
+fn burn_energy(
+    mut q_boids: Populated<(
+        &mut Energy,
+        &LinearVelocity,
+        &SteeringDirection,
+        Option<&BoidParams>,
+    )>,
+    config: Res<SimulationConfig>,
+    time: Res<Time>,
+) {
+    let default_params = BoidParams::default();
+    for (mut energy, vel, steer_direction, params) in q_boids.iter_mut() {
+        let params = params.unwrap_or(&default_params);
+        let speed = vel.length() / params.max_speed(&config);
+        let force = steer_direction.0.length() / params.max_force(&config);
+        let burn = config.energy_idle_burn
+            + config.energy_speed_burn * speed.min(1.)
+            + config.energy_steering_burn * force.min(1.);
+        energy.current = (energy.current - burn * time.delta_secs()).max(0.);
+    }
+}
+
+fn starve_boids(
+    mut q_boids: Populated<(Entity, &mut Energy)>,
+    config: Res<SimulationConfig>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for (ent, mut energy) in q_boids.iter_mut() {
+        if !energy.is_starving() {
+            continue;
+        }
+        energy.starving_for += time.delta_secs();
+        if energy.starving_for >= config.starvation_time {
+            commands.trigger(BoidDied {
+                boid: ent,
+                cause: DeathCause::Starvation,
+            });
+        }
+    }
+}
+
+/// Every boid touching some food takes a bite out of it, until there's
+/// nothing left of it
+fn eat_food(
+    mut q_food: Populated<(Entity, &mut FoodSource, &CollidingEntities)>,
+    mut q_boids: Query<&mut Energy, (With<Boid>, Without<Predator>)>,
+    config: Res<SimulationConfig>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    let bite = config.food_eating_rate * time.delta_secs();
+    for (ent, mut food, colliding) in q_food.iter_mut() {
+        let mut eaters = q_boids.iter_many_mut(colliding.iter());
+        while let Some(mut energy) = eaters.fetch_next() {
+            food.amount -= energy.gain(bite.min(food.amount));
+        }
+        if food.amount <= 0. {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+fn spawn_food(
+    q_food: Query<(), With<FoodSource>>,
+    mut spawner: ResMut<FoodSpawner>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<SimulationRng>,
+    config: Res<SimulationConfig>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    if config.is_changed() {
+        spawner.0.set_duration(Duration::from_secs_f32(
+            config.food_spawn_interval.max(0.01),
+        ));
+    }
+    if !spawner.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    if q_food.iter().count() >= config.max_food_sources {
+        return;
+    }
+    commands.trigger(SpawnTarget::<FoodSource>::new(
+        bounds.random_point(rng.rng()),
+    ));
+}
+
+/// Predators don't eat food, they live off the prey they catch
+fn feed_predator(
+    trigger: Trigger<BoidCaught>,
+    mut q_predators: Query<&mut Energy, With<Predator>>,
+    config: Res<SimulationConfig>,
+) {
+    if let Ok(mut energy) = q_predators.get_mut(trigger.predator) {
+        energy.gain(config.predator_meal_energy);
+    }
+}
+
+/// Shows how much energy the red boid has left, as an arc around it
+fn energy_gizmos(q_special: Single<(&Transform, &Energy), With<SpecialBoid>>, mut gizmos: Gizmos) {
+    let (transform, energy) = *q_special;
+    let color = if energy.is_starving() {
+        Color::srgb(1., 0., 0.)
+    } else {
+        Color::srgb(0.6, 0.8, 0.2)
+    };
+    let angle = TAU * (1. - energy.hunger()).max(0.01);
+    gizmos.arc_2d(
+        Isometry2d::from_translation(transform.translation.truncate()),
+        angle,
+        20.,
+        color,
+    );
+}
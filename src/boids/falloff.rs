@@ -0,0 +1,40 @@
+use bevy::math::curve::{Curve, SampleAutoCurve};
+
+use super::*;
+
+/// How the influence of a flockmate fades with its distance, the distance
+/// being expressed as a fraction of the radius of the behaviour
+#[derive(Reflect, Default, Debug, Clone)]
+pub enum Falloff {
+    /// Every flockmate weighs the same
+    #[default]
+    Constant,
+    /// Fades out evenly, down to nothing at the edge of the radius
+    Linear,
+    /// Grows as the flockmate gets closer, tenfold at a tenth of the radius
+    Inverse,
+    /// Grows as the square of the closeness, making contact much stronger
+    /// than anything else
+    InverseSquare,
+    /// Stays strong up close, then smoothly fades out towards the edge
+    Smoothstep,
+    /// Any curve over `[0, 1]`, sampled at the relative distance
+    Custom(SampleAutoCurve<f32>),
+}
+
+impl Falloff {
+    /// The relative distance under which the inverse falloffs stop growing
+    const MIN_DISTANCE: f32 = 0.1;
+
+    pub fn weight(&self, distance: f32, radius: f32) -> f32 {
+        let t = (distance / radius).clamp(0., 1.);
+        match self {
+            Falloff::Constant => 1.,
+            Falloff::Linear => 1. - t,
+            Falloff::Inverse => 1. / t.max(Self::MIN_DISTANCE),
+            Falloff::InverseSquare => 1. / t.max(Self::MIN_DISTANCE).powi(2),
+            Falloff::Smoothstep => 1. - t * t * (3. - 2. * t),
+            Falloff::Custom(curve) => curve.sample_clamped(t),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+use steering::BoidState;
+use targets::FleeTarget;
+
+pub struct FleePlugin;
+
+impl Plugin for FleePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, flee_behaviour.in_set(FleeSet));
+    }
+}
+
+#[derive(Component)]
+pub struct Flee;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FleeSet;
+
+const TAG: SteeringTag = SteeringTag("Flee");
+const PRIORITY: i32 = 60;
+
+/// The steering that takes `boid` away from `threat_pos` at full speed, the more
+/// urgently the smaller `distance` is. Threats `radius` or further away are ignored
+pub fn flee_from(boid: &BoidState, threat_pos: Vec2, distance: f32, radius: f32) -> Option<Vec2> {
+    if distance >= radius {
+        return None;
+    }
+    let desired_vel = (boid.pos - threat_pos).normalize_or_zero() * boid.max_speed;
+    let urgency = 1. - distance / radius;
+    Some((desired_vel - boid.vel).normalize_or_zero() * urgency)
+}
+
+fn flee_behaviour(
+    mut q_boids: Populated<BoidsQuery, With<Flee>>,
+    q_targets: Populated<&Transform, With<FleeTarget>>,
+    config: Res<SimulationConfig>,
+) {
+    for mut boid in q_boids.iter_mut() {
+        let state = BoidState {
+            pos: boid.transform.translation.truncate(),
+            vel: boid.vel.xy(),
+            max_speed: boid.max_speed(&config),
+        };
+
+        // Only the targets inside the panic radius scare the boid away,
+        // the closer the target is, the stronger the urge to flee
+        let flee_vec = q_targets
+            .iter()
+            .filter_map(|target_tr| {
+                let target_pos = target_tr.translation.truncate();
+                let distance = state.pos.distance(target_pos);
+                flee_from(&state, target_pos, distance, config.flee_panic_radius)
+            })
+            .reduce(|acc, e| acc + e);
+
+        let Some(flee_vec) = flee_vec else {
+            continue;
+        };
+
+        boid.steering.submit(
+            TAG,
+            flee_vec.clamp_length_max(1.) * config.flee_strength,
+            PRIORITY,
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use super::*;
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            follow_flow_field_behaviour
+                .run_if(resource_exists::<FlowField>)
+                .in_set(FollowFlowFieldSet),
+        )
+        .add_systems(
+            Update,
+            flow_field_gizmos.run_if(resource_exists::<FlowField>),
+        );
+    }
+}
+
+/// Makes the boid go with the flow of the [`FlowField`], if there's one
+#[derive(Component)]
+pub struct FollowFlowField;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FollowFlowFieldSet;
+
+const TAG: SteeringTag = SteeringTag("FollowFlowField");
+const PRIORITY: i32 = 20;
+
+/// A grid of direction vectors laid over the world. Vectors are at most of
+/// unit length, shorter ones mean a weaker current
+#[derive(Resource, Clone, Debug)]
+pub struct FlowField {
+    /// The bottom left corner of the grid
+    pub origin: Vec2,
+    pub cell_size: f32,
+    /// The number of columns and rows
+    pub size: UVec2,
+    vectors: Vec<Vec2>,
+}
+
+impl FlowField {
+    /// Builds a field by evaluating `flow` at the centre of each cell
+    pub fn from_fn(
+        origin: Vec2,
+        cell_size: f32,
+        size: UVec2,
+        mut flow: impl FnMut(Vec2) -> Vec2,
+    ) -> Self {
+        let vectors = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .map(|cell| flow(origin + (cell.as_vec2() + 0.5) * cell_size).clamp_length_max(1.))
+            .collect();
+
+        Self {
+            origin,
+            cell_size,
+            size,
+            vectors,
+        }
+    }
+
+    /// A smoothly swirling field, `noise_scale` is the noise frequency per cell,
+    /// so lower values give wider swirls
+    pub fn perlin(origin: Vec2, cell_size: f32, size: UVec2, noise_scale: f32, seed: u64) -> Self {
+        let noise = PerlinNoise::new(seed);
+        Self::from_fn(origin, cell_size, size, |pos| {
+            let angle = noise.sample(pos / cell_size * noise_scale) * std::f32::consts::TAU;
+            Vec2::from_angle(angle)
+        })
+    }
+
+    /// A field pushing away from the surface of the obstacles closer than `influence`,
+    /// fading out the further away from them it gets, and pushing out of them
+    /// from the inside
+    pub fn from_obstacles(
+        spatial_query: &SpatialQuery,
+        origin: Vec2,
+        cell_size: f32,
+        size: UVec2,
+        influence: f32,
+    ) -> Self {
+        let filter = SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacles);
+        Self::from_fn(origin, cell_size, size, |pos| {
+            let Some(projection) = spatial_query.project_point(pos, false, &filter) else {
+                return Vec2::ZERO;
+            };
+            // Inside an obstacle, the way out is towards the surface, at full strength
+            if projection.is_inside {
+                return (projection.point - pos).normalize_or_zero();
+            }
+            let away = pos - projection.point;
+            let falloff = (1. - away.length() / influence).max(0.);
+            away.normalize_or_zero() * falloff
+        })
+    }
+
+    pub fn cell_center(&self, cell: UVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * self.cell_size
+    }
+
+    /// The flow at the given position, `None` outside of the grid
+    pub fn sample(&self, pos: Vec2) -> Option<Vec2> {
+        let cell = ((pos - self.origin) / self.cell_size).floor();
+        if cell.cmplt(Vec2::ZERO).any() || cell.cmpge(self.size.as_vec2()).any() {
+            return None;
+        }
+        let cell = cell.as_uvec2();
+        Some(self.vectors[(cell.y * self.size.x + cell.x) as usize])
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        (0..self.size.y)
+            .flat_map(|y| (0..self.size.x).map(move |x| UVec2::new(x, y)))
+            .zip(self.vectors.iter())
+            .map(|(cell, vector)| (self.cell_center(cell), *vector))
+    }
+}
+
+/// Classic 2D gradient noise, producing values roughly in `[-1, 1]`
+struct PerlinNoise {
+    permutation: [u8; 512],
+}
+
+impl PerlinNoise {
+    fn new(seed: u64) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        let mut permutation = [0; 512];
+        for (i, value) in permutation.iter_mut().enumerate() {
+            *value = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    fn gradient(&self, corner: IVec2) -> Vec2 {
+        let x = (corner.x & 255) as usize;
+        let y = (corner.y & 255) as usize;
+        let hash = self.permutation[self.permutation[x] as usize + y];
+        Vec2::from_angle(hash as f32 / 256. * std::f32::consts::TAU)
+    }
+
+    fn sample(&self, pos: Vec2) -> f32 {
+        let fade = |t: f32| t * t * t * (t * (t * 6. - 15.) + 10.);
+        let cell = pos.floor();
+        let local = pos - cell;
+        let cell = cell.as_ivec2();
+
+        let dot_corner = |offset: IVec2| self.gradient(cell + offset).dot(local - offset.as_vec2());
+        let bottom = dot_corner(IVec2::new(0, 0)).lerp(dot_corner(IVec2::new(1, 0)), fade(local.x));
+        let top = dot_corner(IVec2::new(0, 1)).lerp(dot_corner(IVec2::new(1, 1)), fade(local.x));
+        bottom.lerp(top, fade(local.y))
+    }
+}
+
+fn follow_flow_field_behaviour(
+    mut q_boids: Populated<BoidsQuery, With<FollowFlowField>>,
+    flow_field: Res<FlowField>,
+    config: Res<SimulationConfig>,
+) {
+    for mut boid in q_boids.iter_mut() {
+        let pos = boid.transform.translation.truncate();
+        let predicted_pos = pos + boid.vel.xy().normalize_or_zero() * config.flow_field_lookahead;
+        let Some(flow) = flow_field.sample(predicted_pos) else {
+            continue;
+        };
+        // Dead calm, nothing to follow
+        if flow.length_squared() < f32::EPSILON {
+            continue;
+        }
+
+        let desired_vel = flow * boid.max_speed(&config);
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        boid.steering.submit(
+            TAG,
+            steer * flow.length() * config.flow_field_strength,
+            PRIORITY,
+        );
+    }
+}
+
+fn flow_field_gizmos(flow_field: Res<FlowField>, mut gizmos: Gizmos) {
+    let arrow_length = flow_field.cell_size * 0.4;
+    for (center, flow) in flow_field.cells() {
+        if flow.length_squared() < f32::EPSILON {
+            continue;
+        }
+        gizmos.arrow_2d(
+            center,
+            center + flow * arrow_length,
+            Color::srgba(0.5, 0.7, 1., 0.25),
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use obstacles::Obstacle;
+use seek::arrive_velocity;
+
+use super::*;
+
+pub struct HidePlugin;
+
+impl Plugin for HidePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, hide_behaviour.in_set(HideSet));
+    }
+}
+
+/// Something boids with [`Hide`] take cover from
+#[derive(Component, Default)]
+pub struct Threat;
+
+/// Makes the boid take cover behind the closest obstacle once it spots a [`Threat`]
+#[derive(Component)]
+pub struct Hide;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HideSet;
+
+const TAG: SteeringTag = SteeringTag("Hide");
+const PRIORITY: i32 = 55;
+
+/// The spot right behind the obstacle, as seen from the threat
+fn hiding_spot(obstacle_aabb: &ColliderAabb, threat_pos: Vec2, hide_distance: f32) -> Vec2 {
+    let center = obstacle_aabb.center();
+    let radius = obstacle_aabb.size().max_element() / 2.;
+    center + (center - threat_pos).normalize_or_zero() * (radius + hide_distance)
+}
+
+fn hide_behaviour(
+    mut q_boids: Populated<BoidsQuery, With<Hide>>,
+    q_threats: Populated<&Transform, With<Threat>>,
+    q_obstacles: Query<&ColliderAabb, With<Obstacle>>,
+    spatial_query: SpatialQuery,
+    vision_radius: Res<VisionRadius>,
+    config: Res<SimulationConfig>,
+) {
+    let search_area = Collider::circle(config.hide_search_radius);
+    let filter = SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacles);
+
+    for mut boid in q_boids.iter_mut() {
+        let pos = boid.transform.translation.truncate();
+        let closest_threat = q_threats
+            .iter()
+            .map(|threat_tr| threat_tr.translation.truncate())
+            .filter(|threat_pos| threat_pos.distance(pos) < vision_radius.0)
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)));
+
+        // Nothing to be afraid of
+        let Some(threat_pos) = closest_threat else {
+            continue;
+        };
+
+        let closest_spot = spatial_query
+            .shape_intersections(&search_area, pos, 0., &filter)
+            .into_iter()
+            .filter_map(|ent| q_obstacles.get(ent).ok())
+            .map(|aabb| hiding_spot(aabb, threat_pos, config.hide_distance))
+            .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)));
+
+        // Nowhere to hide
+        let Some(spot) = closest_spot else {
+            continue;
+        };
+
+        let desired_vel = arrive_velocity(
+            spot - pos,
+            boid.max_speed(&config),
+            config.arrive_slowing_radius,
+        );
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        boid.steering
+            .submit(TAG, steer * config.hide_strength, PRIORITY);
+    }
+}
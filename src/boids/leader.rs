@@ -0,0 +1,81 @@
+use super::*;
+use seek::arrive_velocity;
+
+pub struct LeaderPlugin;
+
+impl Plugin for LeaderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, follow_leader_behaviour.in_set(FollowLeaderSet))
+            .add_event::<SetLeader>()
+            .add_observer(set_leader);
+    }
+}
+
+/// An entity the followers trail behind
+#[derive(Component)]
+pub struct Leader;
+
+/// Makes the boid trail behind the closest leader, if there's any
+#[derive(Component)]
+pub struct FollowLeader;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FollowLeaderSet;
+
+const TAG: SteeringTag = SteeringTag("FollowLeader");
+const PRIORITY: i32 = 30;
+
+/// Hands the lead over to the given entity, or disbands the formation with `None`
+#[derive(Event)]
+pub struct SetLeader(pub Option<Entity>);
+
+fn set_leader(
+    trigger: Trigger<SetLeader>,
+    q_leaders: Query<Entity, With<Leader>>,
+    mut commands: Commands,
+) {
+    for ent in q_leaders.iter() {
+        commands.entity(ent).remove::<Leader>();
+    }
+
+    if let Some(new_leader) = trigger.0 {
+        commands.entity(new_leader).insert(Leader);
+    }
+}
+
+fn follow_leader_behaviour(
+    mut q_followers: Populated<BoidsQuery, (With<FollowLeader>, Without<Leader>)>,
+    q_leaders: Populated<(&Transform, &LinearVelocity), With<Leader>>,
+    config: Res<SimulationConfig>,
+) {
+    for mut boid in q_followers.iter_mut() {
+        let pos = boid.transform.translation.truncate();
+        let (leader_tr, leader_vel) = q_leaders
+            .iter()
+            .min_by(|(a, _), (b, _)| {
+                let dist_a = a.translation.truncate().distance_squared(pos);
+                let dist_b = b.translation.truncate().distance_squared(pos);
+                dist_a.total_cmp(&dist_b)
+            })
+            .expect("At least one leader should be found");
+
+        let leader_pos = leader_tr.translation.truncate();
+        let leader_heading = leader_vel.xy().normalize_or_zero();
+        let ahead = leader_pos + leader_heading * config.leader_behind_distance;
+        let behind = leader_pos - leader_heading * config.leader_behind_distance;
+
+        let max_speed = boid.max_speed(&config);
+        // Standing in front of the leader means being in its way, so step aside first
+        let desired_vel = if pos.distance(ahead) < config.leader_sight_radius
+            || pos.distance(leader_pos) < config.leader_sight_radius
+        {
+            (pos - ahead).normalize_or_zero() * max_speed
+        } else {
+            arrive_velocity(behind - pos, max_speed, config.arrive_slowing_radius)
+        };
+
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        boid.steering
+            .submit(TAG, steer * config.leader_following_strength, PRIORITY);
+    }
+}
@@ -0,0 +1,206 @@
+use alignment::Alignment;
+use cohesion::Cohesion;
+use configuration::SimulationRng;
+use energy::Energy;
+use separation::Separation;
+
+use super::*;
+
+pub struct LifecyclePlugin;
+
+impl Plugin for LifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Age>()
+            .add_systems(
+                FixedUpdate,
+                (age_boids, reproduce_boids)
+                    .chain()
+                    .in_set(ServiceSet)
+                    .after(steer_boids),
+            )
+            .add_event::<BoidBorn>()
+            .add_event::<BoidDied>()
+            .add_observer(log_birth)
+            .add_observer(remove_dead_boid);
+    }
+}
+
+/// How long the boid has been around, and how long it gets to live
+#[derive(Component, Reflect, Debug, Clone)]
+#[reflect(Component)]
+pub struct Age {
+    /// The age of the boid, in seconds
+    pub seconds: f32,
+    /// The age at which the boid dies of old age
+    pub lifespan: f32,
+    /// The time, in seconds, since the boid last had offspring
+    since_offspring: f32,
+}
+
+impl Age {
+    pub fn new(lifespan: f32) -> Self {
+        Self {
+            seconds: 0.,
+            lifespan,
+            since_offspring: 0.,
+        }
+    }
+
+    pub fn is_mature(&self, config: &SimulationConfig) -> bool {
+        self.seconds >= config.maturity_age
+    }
+}
+
+/// A boid joined the simulation, either born from `parent` or out of nowhere
+#[derive(Event)]
+pub struct BoidBorn {
+    pub boid: Entity,
+    pub parent: Option<Entity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    OldAge,
+    Starvation,
+    Caught,
+}
+
+/// A boid is about to leave the simulation for good
+#[derive(Event)]
+pub struct BoidDied {
+    pub boid: Entity,
+    pub cause: DeathCause,
+}
+
+impl BoidParams {
+    /// A copy of the params where each of the limits and flocking weights has a
+    /// `mutation_rate` chance of being nudged by up to `mutation_strength` of its
+    /// value, either way. Limits only get overridden once they actually mutate,
+    /// until then they keep following the config
+    pub fn mutated(&self, config: &SimulationConfig, rng: &mut impl Rng) -> Self {
+        let mut mutate = |value: f32| {
+            (rng.gen::<f32>() < config.mutation_rate).then(|| {
+                let strength = config.mutation_strength;
+                value * (1. + rng.gen_range(-strength..=strength)).max(0.)
+            })
+        };
+
+        let mut params = self.clone();
+        params.max_speed = mutate(self.max_speed(config)).or(self.max_speed);
+        params.max_force = mutate(self.max_force.unwrap_or(config.max_force)).or(self.max_force);
+        for tag in [Separation::TAG, Alignment::TAG, Cohesion::TAG] {
+            params.weights.entry(tag).or_insert(1.);
+        }
+        for weight in params.weights.values_mut() {
+            *weight = mutate(*weight).unwrap_or(*weight);
+        }
+        params
+    }
+}
+
+fn age_boids(mut q_boids: Populated<(Entity, &mut Age)>, time: Res<Time>, mut commands: Commands) {
+    for (ent, mut age) in q_boids.iter_mut() {
+        age.seconds += time.delta_secs();
+        age.since_offspring += time.delta_secs();
+        if age.seconds >= age.lifespan {
+            commands.trigger(BoidDied {
+                boid: ent,
+                cause: DeathCause::OldAge,
+            });
+        }
+    }
+}
+
+/// Mature and well-fed boids give some of their energy away to a child of their
+/// own every so often, as long as the world isn't crowded already
+#[allow(clippy::type_complexity)]
+fn reproduce_boids(
+    mut q_boids: Populated<(
+        Entity,
+        &Transform,
+        &mut Age,
+        &mut Energy,
+        &Species,
+        Has<Predator>,
+        Option<&BoidParams>,
+    )>,
+    q_population: Query<(), With<Boid>>,
+    mut rng: ResMut<SimulationRng>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    let mut population = q_population.iter().count();
+    let rng = rng.rng();
+    for (ent, transform, mut age, mut energy, species, predator, params) in q_boids.iter_mut() {
+        if population >= config.max_population {
+            return;
+        }
+        let well_fed = energy.current >= energy.max * config.reproduction_energy_threshold;
+        if !age.is_mature(&config)
+            || !well_fed
+            || age.since_offspring < config.reproduction_interval
+        {
+            continue;
+        }
+
+        age.since_offspring = 0.;
+        energy.current = (energy.current - config.reproduction_energy_cost).max(0.);
+        population += 1;
+
+        // The child shows up right behind its parent
+        let heading = transform.up().truncate();
+        let offset = Vec2::from_angle(rng.gen_range(-FRAC_PI_2..FRAC_PI_2)).rotate(-heading);
+        let params = if predator {
+            params.cloned()
+        } else {
+            Some(params.cloned().unwrap_or_default().mutated(&config, rng))
+        };
+        commands.trigger(SpawnBoid {
+            loc: transform.translation.truncate() + offset * 20.,
+            angle: heading.to_angle(),
+            predator,
+            species: *species,
+            parent: Some(ent),
+            params,
+            ..Default::default()
+        });
+    }
+}
+
+fn log_birth(trigger: Trigger<BoidBorn>) {
+    if let Some(parent) = trigger.parent {
+        debug!("{} was born to {}", trigger.boid, parent);
+    }
+}
+
+/// Takes the dead boid out. The red boid always comes back somewhere else in the
+/// world, unless it was caught and the predators bring it back already
+fn remove_dead_boid(
+    trigger: Trigger<BoidDied>,
+    q_special: Query<&Species, With<SpecialBoid>>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<SimulationRng>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    // The boid may have died of something else earlier in the tick already
+    let Some(entity) = commands.get_entity(trigger.boid) else {
+        return;
+    };
+    debug!("{} died: {:?}", trigger.boid, trigger.cause);
+    entity.despawn_recursive();
+
+    if trigger.cause == DeathCause::Caught && config.respawn_caught_prey {
+        return;
+    }
+    if let Ok(species) = q_special.get(trigger.boid) {
+        let rng = rng.rng();
+        commands.trigger(SpawnBoid {
+            loc: bounds.random_point(rng),
+            angle: rng.gen_range(0. ..TAU),
+            special: true,
+            species: *species,
+            ..Default::default()
+        });
+    }
+}
@@ -1,5 +1,3 @@
-use obstacles::Obstacle;
-
 use super::*;
 
 pub struct ObstacleAvoidancePlugin;
@@ -18,6 +16,9 @@ impl Plugin for ObstacleAvoidancePlugin {
 #[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ObstacleAvoidanceSet;
 
+const TAG: SteeringTag = SteeringTag("ObstacleAvoidance");
+const PRIORITY: i32 = 100;
+
 #[derive(Component)]
 pub struct ObstacleAvoidance;
 
@@ -53,6 +54,7 @@ fn obstacle_avoidance(
             .map(|v| boid.transform.rotation.mul_vec3(v.extend(0.)).xy())
             .collect::<Vec<_>>();
         let mut distance_to_obstacle: f32 = 1.;
+        let mut found = false;
 
         for dir in rotated_directions.iter() {
             let hit_test = spatial_query.cast_ray(
@@ -65,11 +67,13 @@ fn obstacle_avoidance(
 
             // We found a "free" direction and should steer towards it
             if hit_test.is_none() {
-                let desired_steer = dir.normalize() - boid.vel.xy().normalize();
-                boid.dir.0 += desired_steer.normalize() / distance_to_obstacle
-                    * config.obstacle_avoidance_strength;
+                let desired_steer = dir.normalize_or_zero() - boid.vel.xy().normalize_or_zero();
+                let steer = desired_steer.normalize_or_zero() / distance_to_obstacle;
+                boid.steering
+                    .submit(TAG, steer * config.obstacle_avoidance_strength, PRIORITY);
 
                 // Stopping the checks on this one
+                found = true;
                 break;
             }
 
@@ -80,40 +84,30 @@ fn obstacle_avoidance(
         // If we tested all directions and found nothing, the best course of action
         // in my opinion is to just decelerate, since all we know at that point is that
         // there's some obstacle ahead of us
-        boid.dir.0 += -boid.vel.xy().normalize() * config.obstacle_avoidance_strength;
+        if !found {
+            let brake = -boid.vel.xy().normalize_or_zero();
+            boid.steering
+                .submit(TAG, brake * config.obstacle_avoidance_strength, PRIORITY);
+        }
     }
 }
 
 fn obstacle_detection(
     q_boids: Populated<BoidsQueryReadOnly, With<ObstacleAvoidance>>,
     mut commands: Commands,
-    q_vision_cones: Query<BoidVisionQuery>,
-    q_obstacles: Query<Entity, With<Obstacle>>,
     spatial_query: SpatialQuery,
     vision_radius: Res<VisionRadius>,
 ) {
-    for vision_cone in q_vision_cones.iter() {
-        if vision_cone.colliding.is_empty() {
-            continue;
-        }
-
-        let seen_obstacles = vision_cone
-            .colliding
-            .iter()
-            .filter(|e| q_obstacles.contains(**e))
-            .collect::<Vec<_>>();
-
-        if seen_obstacles.is_empty() {
+    for boid in q_boids.iter() {
+        // A standing boid can't run into anything
+        let Ok(heading) = Dir2::new(boid.vel.xy()) else {
+            commands.entity(boid.entity).remove::<AvoidObstacle>();
             continue;
-        }
-
-        let boid = q_boids
-            .get(vision_cone.parent.get())
-            .expect("Should get boid");
+        };
 
         let hit_test = spatial_query.cast_ray(
             boid.transform.translation.xy(),
-            Dir2::new(boid.vel.xy()).expect("The vec shouldn't be of len 0, infinite or nan"),
+            heading,
             vision_radius.0 / 2.,
             false,
             &SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacles),
@@ -0,0 +1,97 @@
+use bevy::{ecs::system::SystemParam, utils::HashMap};
+
+use super::*;
+
+pub struct OcclusionPlugin;
+
+impl Plugin for OcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LineOfSightCache>()
+            .add_systems(FixedUpdate, refresh_line_of_sight.in_set(PerceptionSet));
+    }
+}
+
+/// Whether two boids could see each other the last time a ray was cast between them
+#[derive(Debug, Clone, Copy)]
+struct LineOfSightEntry {
+    visible: bool,
+    tick: u32,
+}
+
+/// The outcome of the raycasts between pairs of boids, kept for a few ticks so
+/// that only a limited number of rays gets cast every tick
+#[derive(Resource, Default, Debug)]
+pub struct LineOfSightCache {
+    entries: HashMap<(Entity, Entity), LineOfSightEntry>,
+    tick: u32,
+    rays_left: usize,
+}
+
+impl LineOfSightCache {
+    /// Visibility is symmetric, so both orders share an entry
+    fn key(a: Entity, b: Entity) -> (Entity, Entity) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+}
+
+/// Tells whether obstacles stand between two boids
+#[derive(SystemParam)]
+pub struct LineOfSight<'w, 's> {
+    cache: ResMut<'w, LineOfSightCache>,
+    spatial_query: SpatialQuery<'w, 's>,
+    config: Res<'w, SimulationConfig>,
+}
+
+impl LineOfSight<'_, '_> {
+    pub fn visible(&mut self, a: Entity, a_pos: Vec2, b: Entity, b_pos: Vec2) -> bool {
+        if !self.config.line_of_sight_occlusion {
+            return true;
+        }
+
+        let key = LineOfSightCache::key(a, b);
+        let cached = self.cache.entries.get(&key).copied();
+        let fresh = cached.is_some_and(|entry| {
+            self.cache.tick - entry.tick < self.config.line_of_sight_cache_ticks
+        });
+        // Out of rays for this tick, the stale answer is better than none, and
+        // a pair never checked before is given the benefit of the doubt
+        if fresh || self.cache.rays_left == 0 {
+            return cached.is_none_or(|entry| entry.visible);
+        }
+
+        let Ok(direction) = Dir2::new(b_pos - a_pos) else {
+            return true;
+        };
+        self.cache.rays_left -= 1;
+        let visible = self
+            .spatial_query
+            .cast_ray(
+                a_pos,
+                direction,
+                a_pos.distance(b_pos),
+                true,
+                &SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacles),
+            )
+            .is_none();
+        let tick = self.cache.tick;
+        self.cache
+            .entries
+            .insert(key, LineOfSightEntry { visible, tick });
+        visible
+    }
+}
+
+pub fn refresh_line_of_sight(mut cache: ResMut<LineOfSightCache>, config: Res<SimulationConfig>) {
+    cache.tick += 1;
+    cache.rays_left = config.line_of_sight_rays_per_tick;
+
+    // Entries nobody asked about for a while most likely belong to boids that
+    // drifted apart or despawned
+    let tick = cache.tick;
+    let max_age = config.line_of_sight_cache_ticks * 4;
+    cache.entries.retain(|_, entry| tick - entry.tick < max_age);
+}
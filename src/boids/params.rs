@@ -0,0 +1,61 @@
+use bevy::utils::HashMap;
+
+use super::*;
+
+pub struct ParamsPlugin;
+
+impl Plugin for ParamsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BoidParams>();
+    }
+}
+
+/// Per-boid overrides of the [`SimulationConfig`], anything left out falls
+/// back to the global value
+#[derive(Component, Default, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct BoidParams {
+    pub max_speed: Option<f32>,
+    /// The maximum force, as a fraction of the boid's own maximum speed
+    pub max_force: Option<f32>,
+    /// Multipliers of the forces submitted by the behaviours
+    pub weights: HashMap<SteeringTag, f32>,
+}
+
+impl BoidParams {
+    pub fn with_max_speed(self, max_speed: f32) -> Self {
+        Self {
+            max_speed: Some(max_speed),
+            ..self
+        }
+    }
+
+    pub fn with_max_force(self, max_force: f32) -> Self {
+        Self {
+            max_force: Some(max_force),
+            ..self
+        }
+    }
+
+    pub fn with_weight(mut self, tag: SteeringTag, weight: f32) -> Self {
+        self.weights.insert(tag, weight);
+        self
+    }
+
+    pub fn max_speed(&self, config: &SimulationConfig) -> f32 {
+        self.max_speed.unwrap_or(config.max_speed)
+    }
+
+    /// The maximum speed of a boid which may or may not have params of its own
+    pub fn max_speed_of(params: Option<&Self>, config: &SimulationConfig) -> f32 {
+        params.map_or(config.max_speed, |params| params.max_speed(config))
+    }
+
+    pub fn max_force(&self, config: &SimulationConfig) -> f32 {
+        self.max_speed(config) * self.max_force.unwrap_or(config.max_force)
+    }
+
+    pub fn weight(&self, tag: SteeringTag) -> f32 {
+        self.weights.get(&tag).copied().unwrap_or(1.)
+    }
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+pub struct PathFollowingPlugin;
+
+impl Plugin for PathFollowingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, follow_path_behaviour.in_set(FollowPathSet));
+    }
+}
+
+/// A route made of straight segments between consecutive points, wrapped in
+/// a corridor the boids try to stay inside of
+#[derive(Component, Clone, Debug)]
+pub struct Path {
+    pub points: Vec<Vec2>,
+    /// Whether the last point connects back to the first one
+    pub looped: bool,
+    /// The half-width of the corridor around the path
+    pub radius: f32,
+}
+
+impl Path {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self {
+            points,
+            looped: false,
+            radius: 20.,
+        }
+    }
+
+    pub fn looped(self) -> Self {
+        Self {
+            looped: true,
+            ..self
+        }
+    }
+
+    pub fn with_radius(self, radius: f32) -> Self {
+        Self { radius, ..self }
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let closing = self
+            .looped
+            .then(|| Some((*self.points.last()?, *self.points.first()?)))
+            .flatten();
+        self.points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+    }
+
+    /// The point of the path closest to `point`, along with the direction of
+    /// the segment it lies on
+    pub fn project(&self, point: Vec2) -> Option<(Vec2, Dir2)> {
+        self.segments()
+            .filter_map(|(start, end)| {
+                let direction = Dir2::new(end - start).ok()?;
+                let along = (point - start)
+                    .dot(*direction)
+                    .clamp(0., start.distance(end));
+                Some((start + direction * along, direction))
+            })
+            .min_by(|(a, _), (b, _)| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+    }
+}
+
+/// Makes the boid travel along the given path entity
+#[derive(Component)]
+pub struct FollowPath(pub Entity);
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FollowPathSet;
+
+const TAG: SteeringTag = SteeringTag("FollowPath");
+const PRIORITY: i32 = 30;
+
+fn follow_path_behaviour(
+    mut q_boids: Populated<(BoidsQuery, &FollowPath)>,
+    q_paths: Query<&Path>,
+    config: Res<SimulationConfig>,
+) {
+    for (mut boid, follow_path) in q_boids.iter_mut() {
+        let Ok(path) = q_paths.get(follow_path.0) else {
+            continue;
+        };
+        let Some(heading) = boid.vel.xy().try_normalize() else {
+            continue;
+        };
+
+        // Only correct the course when the boid is about to leave the corridor
+        let pos = boid.transform.translation.truncate();
+        let predicted_pos = pos + heading * config.path_prediction_distance;
+        let Some((projection, direction)) = path.project(predicted_pos) else {
+            continue;
+        };
+        if predicted_pos.distance(projection) <= path.radius {
+            continue;
+        }
+
+        let target = projection + direction * config.path_lookahead;
+        let desired_vel = (target - pos).normalize_or_zero() * boid.max_speed(&config);
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        boid.steering
+            .submit(TAG, steer * config.path_following_strength, PRIORITY);
+    }
+}
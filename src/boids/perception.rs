@@ -0,0 +1,248 @@
+use bevy::{ecs::system::SystemParam, utils::HashMap};
+use occlusion::{refresh_line_of_sight, LineOfSight};
+
+use super::*;
+
+pub struct PerceptionPlugin {
+    pub search: NeighbourSearch,
+}
+
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.search)
+            .register_type::<NeighbourMode>()
+            .init_resource::<NeighbourGrid>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    sensor_neighbours.run_if(resource_equals(NeighbourSearch::Sensors)),
+                    (rebuild_neighbour_grid, grid_neighbours)
+                        .chain()
+                        .run_if(resource_equals(NeighbourSearch::Grid)),
+                )
+                    .in_set(PerceptionSet)
+                    .after(refresh_line_of_sight),
+            );
+    }
+}
+
+/// Where the boids look for their flockmates
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NeighbourSearch {
+    /// A sensor collider around every boid, letting the physics broadphase find
+    /// the flockmates
+    #[default]
+    Sensors,
+    /// A [`NeighbourGrid`] rebuilt every tick, much lighter for large flocks
+    Grid,
+}
+
+/// Which flockmates make up the neighbourhood of a boid
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighbourMode {
+    /// Everyone within the vision radius
+    #[default]
+    Metric,
+    /// The closest few flockmates of each species, however far away they are.
+    /// With sensors, they still have to be within the sensor to be found
+    KNearest,
+    /// The closest few flockmates of each species within the vision radius
+    KNearestWithinRadius,
+}
+
+impl NeighbourMode {
+    /// How many flockmates of a species a boid perceives at most
+    pub fn max_count(self, config: &SimulationConfig) -> usize {
+        match self {
+            NeighbourMode::Metric => usize::MAX,
+            NeighbourMode::KNearest | NeighbourMode::KNearestWithinRadius => config.neighbour_count,
+        }
+    }
+}
+
+/// Fills the [`Neighbours`] of every boid
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PerceptionSet;
+
+/// The flockmates the boid perceived during the current tick, closest first
+#[derive(Component, Default, Debug)]
+pub struct Neighbours(Vec<Entity>);
+
+impl Neighbours {
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.0.iter()
+    }
+}
+
+/// A uniform spatial hash of the boid positions, with cells as wide as the
+/// vision radius so that a boid only ever needs to look into the 3x3 cells
+/// around its own
+#[derive(Resource, Default, Debug)]
+pub struct NeighbourGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+}
+
+impl NeighbourGrid {
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn rebuild(&mut self, cell_size: f32, boids: impl Iterator<Item = (Entity, Vec2)>) {
+        self.cell_size = cell_size.max(1.);
+        self.cells.clear();
+        for (ent, pos) in boids {
+            let cell = self.cell(pos);
+            self.cells.entry(cell).or_default().push((ent, pos));
+        }
+    }
+
+    /// The boids in the cells exactly `ring` cells away from `center`
+    fn ring(&self, center: IVec2, ring: i32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        (-ring..=ring)
+            .flat_map(move |x| (-ring..=ring).map(move |y| IVec2::new(x, y)))
+            .filter(move |offset| offset.abs().max_element() == ring)
+            .filter_map(move |offset| self.cells.get(&(center + offset)))
+            .flatten()
+            .copied()
+    }
+
+    /// The boids in the cells around `pos`, which includes everything within a
+    /// cell size of it
+    pub fn around(&self, pos: Vec2) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let center = self.cell(pos);
+        (0..=1).flat_map(move |ring| self.ring(center, ring))
+    }
+
+    /// The boids in the rings of cells around `pos`, widened until more than
+    /// `count` of them are found, plus one more ring to catch the ones sitting
+    /// just across the border. Gives up after `max_rings` rings
+    pub fn around_at_least(&self, pos: Vec2, count: usize, max_rings: i32) -> Vec<(Entity, Vec2)> {
+        let center = self.cell(pos);
+        let mut found = Vec::new();
+        let mut last_ring = max_rings;
+        let mut ring = 0;
+        while ring <= last_ring {
+            found.extend(self.ring(center, ring));
+            if found.len() > count && last_ring == max_rings {
+                last_ring = (ring + 1).min(max_rings);
+            }
+            ring += 1;
+        }
+        found
+    }
+}
+
+/// How far, in cells, the grid gets searched for the nearest flockmates
+const K_NEAREST_MAX_RINGS: i32 = 8;
+
+/// Narrows down the candidate flockmates to the ones the boid actually perceives
+#[derive(SystemParam)]
+struct Perception<'w, 's> {
+    line_of_sight: LineOfSight<'w, 's>,
+    q_species: Query<'w, 's, &'static Species>,
+    vision_radius: Res<'w, VisionRadius>,
+    config: Res<'w, SimulationConfig>,
+}
+
+impl Perception<'_, '_> {
+    fn perceive(
+        &mut self,
+        ent: Entity,
+        pos: Vec2,
+        heading: Vec2,
+        candidates: impl Iterator<Item = (Entity, Vec2)>,
+    ) -> Vec<Entity> {
+        let fov = self.config.field_of_view.to_radians();
+        let max_distance = match self.config.neighbour_mode {
+            NeighbourMode::KNearest => f32::INFINITY,
+            _ => self.vision_radius.0,
+        };
+        let max_count = self.config.neighbour_mode.max_count(&self.config);
+
+        let mut perceived = candidates
+            .filter(|(other, _)| *other != ent)
+            .map(|(other, other_pos)| (other, other_pos, pos.distance(other_pos)))
+            .filter(|(_, _, distance)| *distance < max_distance)
+            .filter(|(_, other_pos, _)| in_field_of_view(pos, heading, *other_pos, fov))
+            .collect::<Vec<_>>();
+        perceived.sort_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+        // The count applies to each species on its own, so that the behaviours
+        // which only care about some species still find their closest few
+        // among them. Counted lazily, so that no rays are cast past the closest
+        // visible flockmates of a species
+        let mut counts = HashMap::<Species, usize>::new();
+        perceived
+            .into_iter()
+            .filter(|(other, other_pos, _)| {
+                let species = self.q_species.get(*other).copied().unwrap_or_default();
+                let count = counts.entry(species).or_default();
+                if *count >= max_count || !self.line_of_sight.visible(ent, pos, *other, *other_pos)
+                {
+                    return false;
+                }
+                *count += 1;
+                true
+            })
+            .map(|(other, _, _)| other)
+            .collect()
+    }
+}
+
+fn sensor_neighbours(
+    q_vision_cones: Query<BoidVisionQuery>,
+    q_boids: Query<(&Transform, &LinearVelocity), With<Boid>>,
+    mut q_neighbours: Query<&mut Neighbours>,
+    mut perception: Perception,
+) {
+    for vision_cone in q_vision_cones.iter() {
+        let parent_ent = vision_cone.parent.get();
+        let Ok((transform, vel)) = q_boids.get(parent_ent) else {
+            continue;
+        };
+        let Ok(mut neighbours) = q_neighbours.get_mut(parent_ent) else {
+            continue;
+        };
+
+        let candidates = vision_cone.colliding.iter().filter_map(|ent| {
+            let (other_tr, _) = q_boids.get(*ent).ok()?;
+            Some((*ent, other_tr.translation.truncate()))
+        });
+        let pos = transform.translation.truncate();
+        let heading = boid_heading(transform, vel.xy());
+        neighbours.0 = perception.perceive(parent_ent, pos, heading, candidates);
+    }
+}
+
+fn rebuild_neighbour_grid(
+    q_boids: Query<(Entity, &Transform), With<Boid>>,
+    mut grid: ResMut<NeighbourGrid>,
+    vision_radius: Res<VisionRadius>,
+) {
+    grid.rebuild(
+        vision_radius.0,
+        q_boids
+            .iter()
+            .map(|(ent, transform)| (ent, transform.translation.truncate())),
+    );
+}
+
+fn grid_neighbours(
+    mut q_boids: Query<(Entity, &Transform, &LinearVelocity, &mut Neighbours), With<Boid>>,
+    grid: Res<NeighbourGrid>,
+    mut perception: Perception,
+) {
+    for (ent, transform, vel, mut neighbours) in q_boids.iter_mut() {
+        let pos = transform.translation.truncate();
+        let heading = boid_heading(transform, vel.xy());
+        neighbours.0 = if perception.config.neighbour_mode == NeighbourMode::KNearest {
+            // The closest flockmates may well be out of the surrounding cells
+            let candidates =
+                grid.around_at_least(pos, perception.config.neighbour_count, K_NEAREST_MAX_RINGS);
+            perception.perceive(ent, pos, heading, candidates.into_iter())
+        } else {
+            perception.perceive(ent, pos, heading, grid.around(pos))
+        };
+    }
+}
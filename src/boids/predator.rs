@@ -0,0 +1,195 @@
+use bevy::utils::HashSet;
+use configuration::SimulationRng;
+use hide::Threat;
+use lifecycle::{BoidDied, DeathCause};
+use params::BoidParams;
+use pursuit::{evade_threat, Pursue, EVADE_PRIORITY, EVADE_TAG};
+use species::Species;
+use steering::BoidState;
+
+use super::*;
+
+pub struct PredatorPlugin;
+
+impl Plugin for PredatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (hunt_prey, evade_predators).in_set(PredatorSet),
+                update_predator_params
+                    .run_if(resource_changed::<SimulationConfig>)
+                    .in_set(ConfigurationSet),
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            catch_prey.in_set(ServiceSet).after(steer_boids),
+        )
+        .add_event::<BoidCaught>()
+        .add_observer(handle_caught_boid);
+    }
+}
+
+/// A boid hunting down the rest of the flock
+#[derive(Component)]
+#[require(Threat)]
+pub struct Predator;
+
+/// Makes the boid run away from the predators it sees
+#[derive(Component)]
+pub struct EvadePredators;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PredatorSet;
+
+/// A predator got its hands on a prey
+#[derive(Event)]
+pub struct BoidCaught {
+    pub predator: Entity,
+    pub prey: Entity,
+}
+
+impl BoidParams {
+    /// The limits of the predators, which the prey can barely outrun
+    pub fn predator(config: &SimulationConfig) -> Self {
+        Self::default()
+            .with_max_speed(config.predator_max_speed)
+            .with_max_force(config.predator_max_force)
+    }
+}
+
+fn update_predator_params(
+    mut q_predators: Populated<&mut BoidParams, With<Predator>>,
+    config: Res<SimulationConfig>,
+) {
+    for mut params in q_predators.iter_mut() {
+        params.max_speed = Some(config.predator_max_speed);
+        params.max_force = Some(config.predator_max_force);
+    }
+}
+
+type PreyFilter = (With<Boid>, Without<Predator>);
+
+/// Sets every predator after the closest prey it can see
+fn hunt_prey(
+    q_predators: Populated<(Entity, &Transform, Option<&Pursue>), With<Predator>>,
+    q_prey: Query<(Entity, &Transform), PreyFilter>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    for (ent, transform, pursue) in q_predators.iter() {
+        let pos = transform.translation.truncate();
+        let closest = q_prey
+            .iter()
+            .map(|(prey, prey_tr)| (prey, prey_tr.translation.truncate().distance(pos)))
+            .filter(|(_, distance)| *distance < config.predator_sight_radius)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        match (closest, pursue) {
+            (Some((prey, _)), Some(pursue)) if pursue.0 == prey => {}
+            (Some((prey, _)), _) => {
+                commands.entity(ent).insert(Pursue(prey));
+            }
+            (None, Some(_)) => {
+                commands.entity(ent).remove::<Pursue>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn evade_predators(
+    mut q_boids: Populated<BoidsQuery, (With<EvadePredators>, Without<Predator>)>,
+    q_predators: Populated<(&Transform, &LinearVelocity, Option<&BoidParams>), With<Predator>>,
+    vision_radius: Res<VisionRadius>,
+    config: Res<SimulationConfig>,
+) {
+    for mut boid in q_boids.iter_mut() {
+        let state = BoidState {
+            pos: boid.transform.translation.truncate(),
+            vel: boid.vel.xy(),
+            max_speed: boid.max_speed(&config),
+        };
+
+        // Every predator in sight counts, the closer the scarier
+        let evade_vec = q_predators
+            .iter()
+            .filter_map(|(predator_tr, predator_vel, params)| {
+                let predator = BoidState {
+                    pos: predator_tr.translation.truncate(),
+                    vel: predator_vel.0,
+                    max_speed: BoidParams::max_speed_of(params, &config),
+                };
+                evade_threat(
+                    &state,
+                    &predator,
+                    vision_radius.0,
+                    config.prediction_horizon,
+                )
+            })
+            .reduce(|acc, e| acc + e);
+
+        let Some(evade_vec) = evade_vec else {
+            continue;
+        };
+
+        boid.steering.submit(
+            EVADE_TAG,
+            evade_vec.clamp_length_max(1.) * config.evade_strength,
+            EVADE_PRIORITY,
+        );
+    }
+}
+
+type TouchingPredatorsFilter = (With<Predator>, Changed<CollidingEntities>);
+
+fn catch_prey(
+    q_predators: Populated<(Entity, &CollidingEntities), TouchingPredatorsFilter>,
+    q_prey: Query<(), PreyFilter>,
+    mut commands: Commands,
+) {
+    // A prey touching several predators only gets caught once
+    let mut caught = HashSet::new();
+    for (predator, colliding) in q_predators.iter() {
+        for prey in colliding.iter().filter(|ent| q_prey.contains(**ent)) {
+            if caught.insert(*prey) {
+                commands.trigger(BoidCaught {
+                    predator,
+                    prey: *prey,
+                });
+            }
+        }
+    }
+}
+
+/// Takes the caught prey out, and brings it back somewhere else in the world,
+/// as the same species, unless the config says otherwise
+fn handle_caught_boid(
+    trigger: Trigger<BoidCaught>,
+    q_prey: Query<(Has<SpecialBoid>, &Species)>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<SimulationRng>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    let Ok((special, species)) = q_prey.get(trigger.prey) else {
+        return;
+    };
+    debug!("{} caught {}", trigger.predator, trigger.prey);
+    commands.trigger(BoidDied {
+        boid: trigger.prey,
+        cause: DeathCause::Caught,
+    });
+
+    if config.respawn_caught_prey {
+        let rng = rng.rng();
+        commands.trigger(SpawnBoid {
+            loc: bounds.random_point(rng),
+            angle: rng.gen_range(0. ..std::f32::consts::TAU),
+            special,
+            species: *species,
+            ..Default::default()
+        });
+    }
+}
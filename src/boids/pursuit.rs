@@ -0,0 +1,155 @@
+use super::*;
+use flee::flee_from;
+use steering::BoidState;
+use targets::TrackedVelocity;
+
+pub struct PursuitPlugin;
+
+impl Plugin for PursuitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (pursue_behaviour, evade_behaviour).in_set(PursuitSet),
+        );
+    }
+}
+
+/// Chases the given entity, aiming at where it is going to be rather than
+/// where it currently is
+#[derive(Component)]
+pub struct Pursue(pub Entity);
+
+/// Runs away from the predicted position of the given entity once it's in sight
+#[derive(Component)]
+pub struct Evade(pub Entity);
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PursuitSet;
+
+const PURSUE_TAG: SteeringTag = SteeringTag("Pursue");
+const PURSUE_PRIORITY: i32 = 40;
+pub const EVADE_TAG: SteeringTag = SteeringTag("Evade");
+pub const EVADE_PRIORITY: i32 = 60;
+
+#[derive(QueryData)]
+pub struct MovingEntityQuery {
+    pub transform: &'static Transform,
+    pub vel: Option<&'static LinearVelocity>,
+    pub tracked_vel: Option<&'static TrackedVelocity>,
+    pub params: Option<&'static BoidParams>,
+}
+
+impl MovingEntityQueryItem<'_> {
+    pub fn pos(&self) -> Vec2 {
+        self.transform.translation.truncate()
+    }
+
+    /// Boids carry their own velocity, dragged targets have it estimated,
+    /// everything else is considered standing still
+    pub fn velocity(&self) -> Vec2 {
+        self.vel
+            .map(|v| v.0)
+            .or(self.tracked_vel.map(|v| v.velocity))
+            .unwrap_or_default()
+    }
+
+    pub fn state(&self, config: &SimulationConfig) -> BoidState {
+        BoidState {
+            pos: self.pos(),
+            vel: self.velocity(),
+            max_speed: self.max_speed(config),
+        }
+    }
+
+    /// Boids may go by their own params, everything else by the global limit
+    pub fn max_speed(&self, config: &SimulationConfig) -> f32 {
+        BoidParams::max_speed_of(self.params, config)
+    }
+}
+
+/// Where the quarry will be by the time the pursuer covers the distance to it.
+/// The lookahead grows with distance, but never exceeds the prediction horizon
+pub fn predict_position(
+    pursuer_pos: Vec2,
+    quarry_pos: Vec2,
+    quarry_vel: Vec2,
+    max_speed: f32,
+    horizon: f32,
+) -> Vec2 {
+    let lookahead = (pursuer_pos.distance(quarry_pos) / max_speed).min(horizon);
+    quarry_pos + quarry_vel * lookahead
+}
+
+/// Flees from where `threat` is going to be, as urgently as it is close right now.
+/// The lookahead is based on how fast the evading boid itself can go
+pub fn evade_threat(
+    boid: &BoidState,
+    threat: &BoidState,
+    radius: f32,
+    horizon: f32,
+) -> Option<Vec2> {
+    let future_pos = predict_position(boid.pos, threat.pos, threat.vel, boid.max_speed, horizon);
+    flee_from(boid, future_pos, boid.pos.distance(threat.pos), radius)
+}
+
+fn pursue_behaviour(
+    q_pursuers: Populated<(Entity, &Pursue)>,
+    q_moving: Query<MovingEntityQuery>,
+    mut q_steering: Query<&mut SteeringForces>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    for (ent, pursue) in q_pursuers.iter() {
+        let Ok(quarry) = q_moving.get(pursue.0) else {
+            // The quarry is gone, nothing left to chase
+            commands.entity(ent).remove::<Pursue>();
+            continue;
+        };
+        let pursuer = q_moving.get(ent).expect("The pursuer should be present");
+
+        let future_pos = predict_position(
+            pursuer.pos(),
+            quarry.pos(),
+            quarry.velocity(),
+            pursuer.max_speed(&config),
+            config.prediction_horizon,
+        );
+        let desired_vel =
+            (future_pos - pursuer.pos()).normalize_or_zero() * pursuer.max_speed(&config);
+        let steer = (desired_vel - pursuer.velocity()).normalize_or_zero();
+
+        if let Ok(mut steering) = q_steering.get_mut(ent) {
+            steering.submit(PURSUE_TAG, steer * config.pursuit_strength, PURSUE_PRIORITY);
+        }
+    }
+}
+
+fn evade_behaviour(
+    q_evaders: Populated<(Entity, &Evade)>,
+    q_moving: Query<MovingEntityQuery>,
+    mut q_steering: Query<&mut SteeringForces>,
+    vision_radius: Res<VisionRadius>,
+    config: Res<SimulationConfig>,
+    mut commands: Commands,
+) {
+    for (ent, evade) in q_evaders.iter() {
+        let Ok(threat) = q_moving.get(evade.0) else {
+            commands.entity(ent).remove::<Evade>();
+            continue;
+        };
+        let evader = q_moving.get(ent).expect("The evader should be present");
+
+        let Some(steer) = evade_threat(
+            &evader.state(&config),
+            &threat.state(&config),
+            vision_radius.0,
+            config.prediction_horizon,
+        ) else {
+            continue;
+        };
+
+        if let Ok(mut steering) = q_steering.get_mut(ent) {
+            steering.submit(EVADE_TAG, steer * config.evade_strength, EVADE_PRIORITY);
+        }
+    }
+}
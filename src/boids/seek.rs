@@ -1,75 +1,87 @@
 use super::{
-    targets::SeekTarget, App, BoidVisionQuery, BoidsQuery, Commands, Component, FixedUpdate,
-    MeshPickingPlugin, Plugin, Populated, Res, SimulationConfig, SystemSet, Transform,
-    Vec2Swizzles, With,
+    energy::Energy,
+    targets::{FoodSource, SeekTarget},
+    App, BoidsQuery, Commands, Component, Entity, FixedUpdate, IntoSystemConfigs,
+    MeshPickingPlugin, Plugin, Populated, Query, Res, SimulationConfig, SteeringTag, SystemSet,
+    Transform, Vec2, Vec2Swizzles, With,
 };
 pub struct SeekPlugin;
 
 impl Plugin for SeekPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, seek_behaviour)
-            .add_plugins(MeshPickingPlugin::default());
+        app.add_systems(FixedUpdate, seek_behaviour.in_set(SeekSet))
+            .add_plugins(MeshPickingPlugin);
     }
 }
 
 #[derive(Component)]
 pub struct Seek;
 
+/// Makes a seeking boid slow down when approaching its target instead of
+/// overshooting it
+#[derive(Component)]
+pub struct Arrive;
+
 #[derive(Component)]
 pub struct Chasing;
 
 #[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SeekSet;
 
+const TAG: SteeringTag = SteeringTag("Seek");
+const PRIORITY: i32 = 40;
+
+/// Seeks the targets, and the nearby food once the boid gets hungry enough.
+/// The hungrier the boid, the stronger the pull
 fn seek_behaviour(
-    mut q_boids: Populated<BoidsQuery, With<Seek>>,
-    q_targets: Populated<&Transform, With<SeekTarget>>,
-    q_vision_cones: Populated<BoidVisionQuery>,
+    mut q_boids: Populated<(BoidsQuery, Option<&Energy>), With<Seek>>,
+    q_targets: Query<&Transform, With<SeekTarget>>,
+    q_food: Query<&Transform, With<FoodSource>>,
+    q_arriving: Query<Entity, With<Arrive>>,
     config: Res<SimulationConfig>,
     mut commands: Commands,
 ) {
-    for vision_cone in q_vision_cones.iter() {
-        // if vision_cone.colliding.is_empty() {
-        //     continue;
-        // }
+    for (mut boid, energy) in q_boids.iter_mut() {
+        let parent_ent = boid.entity;
+        let pos = boid.transform.translation.truncate();
+        let hunger = energy.map_or(0., Energy::hunger);
 
-        // let seen_targets = vision_cone
-        //     .colliding
-        //     .iter()
-        //     .filter_map(|ent| q_targets.get(*ent).ok())
-        //     .collect::<Vec<_>>();
-        let seen_targets = q_targets.iter().collect::<Vec<&Transform>>();
-
-        let parent_ent = vision_cone.parent.get();
-        let mut boid = q_boids
-            .get_mut(parent_ent)
-            .expect("The boid should be present");
+        let food = q_food
+            .iter()
+            .filter(|_| hunger > config.hunger_threshold)
+            .filter(|food_tr| {
+                food_tr.translation.truncate().distance(pos) < config.food_sight_radius
+            });
+        let closest_target = q_targets
+            .iter()
+            .chain(food)
+            .map(|target_tr| target_tr.translation.truncate())
+            .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)));
 
-        if seen_targets.is_empty() {
+        let Some(closest_target) = closest_target else {
             commands.entity(parent_ent).remove::<Chasing>();
             continue;
-        } else {
-            commands.entity(parent_ent).insert(Chasing);
-        }
-
-        let closest_target_tr = seen_targets
-            .iter()
-            .reduce(|acc, e| {
-                let distance = boid.transform.translation.distance(e.translation);
-                let cur_best_distance = boid.transform.translation.distance(acc.translation);
-                if distance < cur_best_distance {
-                    e
-                } else {
-                    acc
-                }
-            })
-            .expect("At least one target should be found");
+        };
+        commands.entity(parent_ent).insert(Chasing);
 
-        let desired_vel = (closest_target_tr.translation - boid.transform.translation)
-            .truncate()
-            .normalize()
-            * config.max_speed;
+        let max_speed = boid.max_speed(&config);
+        let to_target = closest_target - pos;
+        let desired_vel = if q_arriving.contains(parent_ent) {
+            arrive_velocity(to_target, max_speed, config.arrive_slowing_radius)
+        } else {
+            to_target.normalize_or_zero() * max_speed
+        };
 
-        boid.dir.0 += (desired_vel - boid.vel.xy()).normalize() * config.seek_strength;
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        let strength = config.seek_strength * (1. + hunger * config.hunger_seek_boost);
+        boid.steering.submit(TAG, steer * strength, PRIORITY);
     }
 }
+
+/// The velocity that brings a boid to a point `to_target` away from it: full speed
+/// outside of the slowing radius, linearly ramping down to zero inside of it
+pub fn arrive_velocity(to_target: Vec2, max_speed: f32, slowing_radius: f32) -> Vec2 {
+    let distance = to_target.length();
+    let ramped_speed = max_speed * (distance / slowing_radius).min(1.);
+    to_target.normalize_or_zero() * ramped_speed
+}
@@ -1,57 +1,60 @@
-use super::{BoidVisionQuery, BoidsQuery, SimulationConfig};
+use super::{
+    species::SpeciesInteraction,
+    steering::{BoidState, SteeringBehaviour, SteeringBehaviourAppExt},
+    SimulationConfig, SteeringTag,
+};
 use crate::prelude::*;
 
 pub struct SeparationPlugin;
 
-#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct SeparationSet;
-
 impl Plugin for SeparationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, separation_behaviour.in_set(SeparationSet));
+        app.add_steering_behaviour::<Separation>();
     }
 }
 
 #[derive(Component)]
 pub struct Separation;
 
-fn separation_behaviour(
-    mut q_boids: Populated<BoidsQuery, With<Separation>>,
-    q_vision_cones: Query<BoidVisionQuery>,
-    config: Res<SimulationConfig>,
-) {
-    for vision_cone in q_vision_cones.iter() {
-        if vision_cone.colliding.is_empty() {
-            continue;
-        }
+impl SteeringBehaviour for Separation {
+    const TAG: SteeringTag = SteeringTag("Separation");
+    const PRIORITY: i32 = 70;
 
-        let seen_boids = vision_cone
-            .colliding
-            .iter()
-            .filter_map(|ent| q_boids.get(*ent).ok())
-            .collect::<Vec<_>>();
+    fn weight(config: &SimulationConfig) -> f32 {
+        config.separation_strength
+    }
 
-        if seen_boids.is_empty() {
-            continue;
-        }
+    fn interacts_with(interaction: SpeciesInteraction) -> bool {
+        matches!(
+            interaction,
+            SpeciesInteraction::Flock | SpeciesInteraction::SeparateOnly
+        )
+    }
 
-        let parent_ent = vision_cone.parent.get();
-        let boid = q_boids.get(parent_ent).expect("The boid should be present");
+    fn radius(config: &SimulationConfig) -> f32 {
+        config.vision_distance() * config.separation_radius_rel
+    }
 
-        let avoidance_vec = seen_boids
+    fn steer(
+        boid: &BoidState,
+        neighbours: &[BoidState],
+        config: &SimulationConfig,
+    ) -> Option<Vec2> {
+        let radius = Self::radius(config);
+        let avoidance_vec = neighbours
             .iter()
             .map(|other| {
-                let distance = boid
-                    .transform
-                    .translation
-                    .distance(other.transform.translation);
-                let avoid_dir = boid.transform.translation - other.transform.translation;
-                avoid_dir.truncate().normalize() / distance
+                let away = boid.pos - other.pos;
+                away.normalize_or_zero() * config.separation_falloff.weight(away.length(), radius)
             })
-            .reduce(|acc, e| acc + e)
-            .expect("Should get a vec");
+            .sum::<Vec2>();
+        if avoidance_vec.length_squared() == 0. {
+            return None;
+        }
 
-        let mut boid = q_boids.get_mut(parent_ent).expect("Should get boid");
-        boid.dir.0 += avoidance_vec.clamp_length_min(1.) * config.separation_strength;
+        // Capped like flee and evade: with the default inverse falloff every
+        // flockmate in the radius weighs at least 1, so a crowded boid pushes
+        // away at full strength without drowning out the other behaviours
+        Some(avoidance_vec.clamp_length_max(1.))
     }
 }
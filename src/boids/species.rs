@@ -0,0 +1,148 @@
+use bevy::utils::HashMap;
+use flee::flee_from;
+use params::BoidParams;
+use steering::{BoidState, SteeringBehaviour, SteeringBehaviourAppExt};
+
+use super::*;
+
+pub struct SpeciesPlugin;
+
+impl Plugin for SpeciesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpeciesRegistry>()
+            .register_type::<Species>()
+            .add_steering_behaviour::<FleeSpecies>();
+    }
+}
+
+/// The species a boid belongs to, as registered in the [`SpeciesRegistry`]
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct Species(pub usize);
+
+/// How boids of one species treat the boids of another one
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeciesInteraction {
+    /// As if they weren't there
+    Ignore,
+    /// Only keep their distance
+    #[default]
+    SeparateOnly,
+    /// Separate, align and stick together, like with their own kind
+    Flock,
+    /// Run away from them
+    Flee,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpeciesInfo {
+    pub name: String,
+    pub color: Color,
+    /// The size of the boid mesh
+    pub scale: f32,
+    /// Overrides of the simulation config shared by the whole species
+    pub params: Option<BoidParams>,
+}
+
+impl SpeciesInfo {
+    pub fn new(name: impl Into<String>, color: Color) -> Self {
+        Self {
+            name: name.into(),
+            color,
+            scale: 10.,
+            params: None,
+        }
+    }
+
+    pub fn with_scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn with_params(self, params: BoidParams) -> Self {
+        Self {
+            params: Some(params),
+            ..self
+        }
+    }
+}
+
+/// Every species of the simulation, along with how they get along. The first
+/// one is the species boids belong to unless told otherwise
+#[derive(Resource, Debug)]
+pub struct SpeciesRegistry {
+    species: Vec<SpeciesInfo>,
+    interactions: HashMap<(Species, Species), SpeciesInteraction>,
+}
+
+impl Default for SpeciesRegistry {
+    fn default() -> Self {
+        Self {
+            species: vec![SpeciesInfo::new("Teal", Color::srgb_u8(2, 128, 144))],
+            interactions: HashMap::default(),
+        }
+    }
+}
+
+impl SpeciesRegistry {
+    pub fn register(&mut self, info: SpeciesInfo) -> Species {
+        self.species.push(info);
+        Species(self.species.len() - 1)
+    }
+
+    pub fn get(&self, species: Species) -> Option<&SpeciesInfo> {
+        self.species.get(species.0)
+    }
+
+    /// Sets how `species` treats `other`, the other way around is left untouched
+    pub fn set_interaction(
+        &mut self,
+        species: Species,
+        other: Species,
+        interaction: SpeciesInteraction,
+    ) {
+        self.interactions.insert((species, other), interaction);
+    }
+
+    /// How `species` treats `other`. By default, boids flock with their own
+    /// kind and only keep their distance from the others
+    pub fn interaction(&self, species: Species, other: Species) -> SpeciesInteraction {
+        self.interactions
+            .get(&(species, other))
+            .copied()
+            .unwrap_or(if species == other {
+                SpeciesInteraction::Flock
+            } else {
+                SpeciesInteraction::SeparateOnly
+            })
+    }
+}
+
+/// Runs away from the flockmates of the species the boid's own one flees from
+#[derive(Component)]
+pub struct FleeSpecies;
+
+impl SteeringBehaviour for FleeSpecies {
+    const TAG: SteeringTag = SteeringTag("FleeSpecies");
+    const PRIORITY: i32 = 60;
+
+    fn weight(config: &SimulationConfig) -> f32 {
+        config.flee_strength
+    }
+
+    fn interacts_with(interaction: SpeciesInteraction) -> bool {
+        interaction == SpeciesInteraction::Flee
+    }
+
+    fn steer(
+        boid: &BoidState,
+        neighbours: &[BoidState],
+        config: &SimulationConfig,
+    ) -> Option<Vec2> {
+        let radius = Self::radius(config);
+        let flee_vec = neighbours
+            .iter()
+            .filter_map(|other| flee_from(boid, other.pos, boid.pos.distance(other.pos), radius))
+            .reduce(|acc, e| acc + e)?;
+        Some(flee_vec.clamp_length_max(1.))
+    }
+}
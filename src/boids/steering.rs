@@ -0,0 +1,369 @@
+use std::{hash::Hash, marker::PhantomData};
+
+use bevy::utils::HashMap;
+use configuration::SimulationRng;
+use falloff::Falloff;
+use params::BoidParams;
+use perception::{NeighbourMode, Neighbours};
+use species::{Species, SpeciesInteraction, SpeciesRegistry};
+
+use super::*;
+
+pub struct SteeringPlugin;
+
+impl Plugin for SteeringPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CombinationStrategy>()
+            .register_type::<Falloff>()
+            .register_type::<SteeringContributions>();
+    }
+}
+
+/// Names the behaviour a steering force comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct SteeringTag(pub &'static str);
+
+impl SteeringTag {
+    /// A stable colour to tell the behaviours apart in the gizmos
+    pub fn color(&self) -> Color {
+        let hue = self.0.bytes().map(u32::from).sum::<u32>() * 47 % 360;
+        Color::hsl(hue as f32, 0.8, 0.6)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SteeringForce {
+    pub tag: SteeringTag,
+    pub force: Vec2,
+    /// Forces with higher priorities get served first by the prioritized strategies
+    pub priority: i32,
+}
+
+/// The forces submitted by the behaviours during the current tick, waiting to be
+/// combined into the [`SteeringDirection`]. Cleared at the start of every tick
+#[derive(Component, Default, Debug)]
+pub struct SteeringForces(Vec<SteeringForce>);
+
+impl SteeringForces {
+    /// Adds a force on behalf of a behaviour. Several submissions from the same
+    /// behaviour are summed up
+    pub fn submit(&mut self, tag: SteeringTag, force: Vec2, priority: i32) {
+        if let Some(existing) = self.0.iter_mut().find(|f| f.tag == tag) {
+            existing.force += force;
+            return;
+        }
+        self.0.push(SteeringForce {
+            tag,
+            force,
+            priority,
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SteeringForce> {
+        self.0.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// How the forces submitted by the behaviours are turned into a single one
+#[derive(Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombinationStrategy {
+    /// Everything is summed up, then truncated to the maximum force
+    #[default]
+    WeightedTruncatedSum,
+    /// Forces are added from the highest priority down, until the maximum force is used up
+    PrioritizedTruncatedSum,
+    /// The highest priority force is picked with a fixed probability, otherwise
+    /// the next one gets the chance, and so on
+    PrioritizedDithering,
+}
+
+/// Per-boid arbitration settings, anything left out falls back to the
+/// [`SimulationConfig`] and the priorities the behaviours submitted with
+#[derive(Component, Default, Debug)]
+pub struct SteeringArbitration {
+    pub strategy: Option<CombinationStrategy>,
+    pub priorities: HashMap<SteeringTag, i32>,
+}
+
+/// What each behaviour asked for during the last tick, and how much of it made
+/// it through the arbitration
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct SteeringContributions(pub Vec<SteeringContribution>);
+
+#[derive(Debug, Clone, Copy, Reflect)]
+pub struct SteeringContribution {
+    pub tag: SteeringTag,
+    pub requested: Vec2,
+    pub applied: Vec2,
+}
+
+impl SteeringContributions {
+    /// The behaviour that had the most say in the last steering decision
+    pub fn dominant(&self) -> Option<&SteeringContribution> {
+        self.0
+            .iter()
+            .max_by(|a, b| a.applied.length().total_cmp(&b.applied.length()))
+    }
+}
+
+impl CombinationStrategy {
+    /// Works out the share of each force in the combined one. The forces are
+    /// expected to be sorted by descending priority
+    pub fn combine(
+        &self,
+        forces: &[SteeringForce],
+        max_force: f32,
+        dithering_probability: f32,
+        rng: &mut impl Rng,
+    ) -> Vec<Vec2> {
+        match self {
+            CombinationStrategy::WeightedTruncatedSum => {
+                let total = forces.iter().map(|f| f.force).sum::<Vec2>();
+                let scale = if total.length() > max_force {
+                    max_force / total.length()
+                } else {
+                    1.
+                };
+                forces.iter().map(|f| f.force * scale).collect()
+            }
+            CombinationStrategy::PrioritizedTruncatedSum => {
+                let mut total = Vec2::ZERO;
+                forces
+                    .iter()
+                    .map(|f| {
+                        let remaining = (max_force - total.length()).max(0.);
+                        let applied = f.force.clamp_length_max(remaining);
+                        total += applied;
+                        applied
+                    })
+                    .collect()
+            }
+            CombinationStrategy::PrioritizedDithering => {
+                let picked = forces.iter().position(|f| {
+                    f.force.length_squared() > f32::EPSILON
+                        && rng.gen_bool(dithering_probability.clamp(0., 1.) as f64)
+                });
+                forces
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| {
+                        if Some(i) == picked {
+                            f.force.clamp_length_max(max_force)
+                        } else {
+                            Vec2::ZERO
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub fn reset_steering(mut q_boids: Populated<(&mut SteeringForces, &mut SteeringDirection)>) {
+    for (mut forces, mut dir) in q_boids.iter_mut() {
+        forces.clear();
+        dir.0 = Vec2::ZERO;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn arbitrate_steering(
+    mut q_boids: Populated<(
+        &SteeringForces,
+        &mut SteeringDirection,
+        &mut SteeringContributions,
+        Option<&SteeringArbitration>,
+        Option<&BoidParams>,
+    )>,
+    mut rng: ResMut<SimulationRng>,
+    max_force: Res<MaxForce>,
+    config: Res<SimulationConfig>,
+) {
+    for (forces, mut dir, mut contributions, arbitration, params) in q_boids.iter_mut() {
+        let mut sorted = forces.iter().copied().collect::<Vec<_>>();
+        if let Some(params) = params {
+            for force in sorted.iter_mut() {
+                force.force *= params.weight(force.tag);
+            }
+        }
+        if let Some(arbitration) = arbitration {
+            for force in sorted.iter_mut() {
+                if let Some(priority) = arbitration.priorities.get(&force.tag) {
+                    force.priority = *priority;
+                }
+            }
+        }
+        sorted.sort_by_key(|f| std::cmp::Reverse(f.priority));
+
+        let strategy = arbitration
+            .and_then(|a| a.strategy)
+            .unwrap_or(config.steering_strategy);
+        let applied = strategy.combine(
+            &sorted,
+            params.map_or(max_force.0, |params| params.max_force(&config)),
+            config.dithering_probability,
+            rng.rng(),
+        );
+
+        dir.0 = applied.iter().sum();
+        contributions.0 = sorted
+            .iter()
+            .zip(applied)
+            .map(|(force, applied)| SteeringContribution {
+                tag: force.tag,
+                requested: force.force,
+                applied,
+            })
+            .collect();
+    }
+}
+
+/// What a boid knows about itself or about a flockmate it sees
+#[derive(Debug, Clone, Copy)]
+pub struct BoidState {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    /// How fast the boid wants to go at most, its own params taken into account
+    pub max_speed: f32,
+}
+
+/// A steering rule that works out its force from the flockmates a boid sees.
+/// Registered with [`SteeringBehaviourAppExt::add_steering_behaviour`], it runs
+/// for every boid carrying the implementing component
+pub trait SteeringBehaviour: Component {
+    const TAG: SteeringTag;
+    const PRIORITY: i32;
+
+    /// The multiplier applied to the force returned by [`SteeringBehaviour::steer`]
+    fn weight(config: &SimulationConfig) -> f32;
+
+    /// How far from the boid flockmates still count for this behaviour. Past the
+    /// vision radius, flockmates only count in the k-nearest mode
+    fn radius(config: &SimulationConfig) -> f32 {
+        config.vision_distance()
+    }
+
+    /// Whether flockmates of a species treated that way count, by default only
+    /// the ones the boid flocks with
+    fn interacts_with(interaction: SpeciesInteraction) -> bool {
+        interaction == SpeciesInteraction::Flock
+    }
+
+    /// Whether the behaviour runs at all, by default only when it has some weight
+    fn enabled(config: &SimulationConfig) -> bool {
+        Self::weight(config) > 0.
+    }
+
+    /// The unweighted steering force, or `None` when the behaviour has nothing
+    /// to say. Only called when the boid sees at least one flockmate
+    fn steer(boid: &BoidState, neighbours: &[BoidState], config: &SimulationConfig)
+        -> Option<Vec2>;
+}
+
+/// The set the systems of the steering behaviour `B` run in
+#[derive(SystemSet)]
+pub struct SteeringBehaviourSet<B>(PhantomData<B>);
+
+impl<B> Default for SteeringBehaviourSet<B> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<B> Clone for SteeringBehaviourSet<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B> Copy for SteeringBehaviourSet<B> {}
+
+impl<B> PartialEq for SteeringBehaviourSet<B> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<B> Eq for SteeringBehaviourSet<B> {}
+
+impl<B> Hash for SteeringBehaviourSet<B> {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<B> std::fmt::Debug for SteeringBehaviourSet<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SteeringBehaviourSet<{}>", std::any::type_name::<B>())
+    }
+}
+
+pub trait SteeringBehaviourAppExt {
+    /// Runs the steering behaviour `B` alongside the other behaviours, in its
+    /// own [`SteeringBehaviourSet`]
+    fn add_steering_behaviour<B: SteeringBehaviour>(&mut self) -> &mut Self;
+}
+
+impl SteeringBehaviourAppExt for App {
+    fn add_steering_behaviour<B: SteeringBehaviour>(&mut self) -> &mut Self {
+        self.configure_sets(
+            FixedUpdate,
+            SteeringBehaviourSet::<B>::default().in_set(BehaviourSet),
+        )
+        .add_systems(
+            FixedUpdate,
+            steering_behaviour::<B>
+                .run_if(|config: Res<SimulationConfig>| B::enabled(&config))
+                .in_set(SteeringBehaviourSet::<B>::default()),
+        )
+    }
+}
+
+fn steering_behaviour<B: SteeringBehaviour>(
+    mut q_boids: Populated<(BoidsQuery, &Neighbours, &Species), With<B>>,
+    registry: Res<SpeciesRegistry>,
+    config: Res<SimulationConfig>,
+) {
+    let state = |boid: &BoidsQueryReadOnlyItem| BoidState {
+        pos: boid.transform.translation.truncate(),
+        vel: boid.vel.xy(),
+        max_speed: boid.max_speed(&config),
+    };
+
+    // Topological neighbourhoods aren't bound by any distance
+    let radius = match config.neighbour_mode {
+        NeighbourMode::KNearest => f32::INFINITY,
+        _ => B::radius(&config),
+    };
+    let max_count = config.neighbour_mode.max_count(&config);
+
+    let forces = q_boids
+        .iter()
+        .filter_map(|(boid, neighbours, species)| {
+            let own = state(&boid);
+            let neighbours = neighbours
+                .iter()
+                .filter_map(|ent| q_boids.get(*ent).ok())
+                .filter(|(_, _, other_species)| {
+                    B::interacts_with(registry.interaction(*species, **other_species))
+                })
+                .map(|(other, _, _)| state(&other))
+                .filter(|other| own.pos.distance(other.pos) < radius)
+                .take(max_count)
+                .collect::<Vec<_>>();
+            if neighbours.is_empty() {
+                return None;
+            }
+            Some((boid.entity, B::steer(&own, &neighbours, &config)?))
+        })
+        .collect::<Vec<_>>();
+
+    for (ent, force) in forces {
+        let (mut boid, _, _) = q_boids.get_mut(ent).expect("Should get boid");
+        boid.steering
+            .submit(B::TAG, force * B::weight(&config), B::PRIORITY);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{input::move_drag, prelude::*};
 use std::marker::PhantomData;
 
-use super::BoidsQuery;
+use super::{hide::Threat, BoidsQuery};
 
 pub struct TargetPlugin;
 
@@ -9,9 +9,14 @@ impl Plugin for TargetPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SpawnTarget<SeekTarget>>()
             .add_event::<SpawnTarget<FleeTarget>>()
+            .add_event::<SpawnTarget<FoodSource>>()
             .add_observer(spawn_target::<SeekTarget>)
             .add_observer(spawn_target::<FleeTarget>)
-            .add_systems(FixedUpdate, despawn_targets::<FleeTarget>);
+            .add_observer(spawn_target::<FoodSource>)
+            .add_systems(
+                FixedUpdate,
+                (despawn_targets::<FleeTarget>, track_target_velocity),
+            );
     }
 }
 
@@ -19,10 +24,29 @@ impl Plugin for TargetPlugin {
 pub struct SeekTarget;
 
 #[derive(Component, Default)]
+#[require(Threat)]
 pub struct FleeTarget;
 
+/// Something to eat, boids touching it get their energy back until it runs out
+#[derive(Component)]
+pub struct FoodSource {
+    /// The energy left to eat
+    pub amount: f32,
+}
+
+impl Default for FoodSource {
+    fn default() -> Self {
+        Self { amount: 150. }
+    }
+}
+
+/// Targets aren't physics bodies and are moved around by dragging, so their
+/// velocity is estimated from the displacement between two fixed ticks
 #[derive(Component, Default)]
-pub struct Obstacle;
+pub struct TrackedVelocity {
+    pub velocity: Vec2,
+    last_pos: Option<Vec2>,
+}
 
 #[derive(Component, Default)]
 pub struct Target<T> {
@@ -69,13 +93,16 @@ fn spawn_target<T>(
             Target::<T>::collider(),
             Target::<T>::collision_layers(),
             CollidingEntities::default(),
+            TrackedVelocity::default(),
         ))
         .observe(move_drag);
 }
 
+type TouchedTargetsFilter<T> = (With<Target<T>>, Changed<CollidingEntities>);
+
 fn despawn_targets<T>(
     mut commands: Commands,
-    q_targets: Query<(Entity, &CollidingEntities), (With<Target<T>>, Changed<CollidingEntities>)>,
+    q_targets: Query<(Entity, &CollidingEntities), TouchedTargetsFilter<T>>,
     q_boids: Populated<BoidsQuery>,
 ) where
     Target<T>: Component,
@@ -91,6 +118,19 @@ fn despawn_targets<T>(
     }
 }
 
+fn track_target_velocity(
+    mut q_targets: Populated<(&Transform, &mut TrackedVelocity)>,
+    time: Res<Time>,
+) {
+    for (transform, mut tracked) in q_targets.iter_mut() {
+        let pos = transform.translation.truncate();
+        if let Some(last_pos) = tracked.last_pos {
+            tracked.velocity = (pos - last_pos) / time.delta_secs();
+        }
+        tracked.last_pos = Some(pos);
+    }
+}
+
 trait BoidTarget {
     fn color() -> Color;
 
@@ -125,3 +165,13 @@ impl BoidTarget for Target<FleeTarget> {
         Color::srgb(1., 0., 0.)
     }
 }
+
+impl BoidTarget for Target<FoodSource> {
+    fn color() -> Color {
+        Color::srgb(0.6, 0.8, 0.2)
+    }
+
+    fn radius() -> f32 {
+        14.
+    }
+}
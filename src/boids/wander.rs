@@ -0,0 +1,71 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::*;
+use pursuit::Pursue;
+use seek::Chasing;
+
+pub struct WanderPlugin;
+
+impl Plugin for WanderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, wander_behaviour.in_set(WanderSet));
+    }
+}
+
+/// Makes an idle boid roam around by steering towards a target that randomly
+/// drifts along a circle placed in front of it
+#[derive(Component, Default)]
+pub struct Wander {
+    /// The position of the wander target on the circle, relative to the heading
+    pub angle: f32,
+    /// The boid's own random generator, seeded from the simulation seed and the
+    /// boid the first time it wanders, so that its jitter doesn't depend on the
+    /// order the other boids are processed in
+    rng: Option<StdRng>,
+}
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WanderSet;
+
+const TAG: SteeringTag = SteeringTag("Wander");
+const PRIORITY: i32 = 0;
+
+impl Wander {
+    /// The wander target relative to the boid, given its heading
+    pub fn target(&self, heading: Vec2, config: &SimulationConfig) -> Vec2 {
+        let circle_center = heading * config.wander_circle_distance;
+        let offset =
+            Vec2::from_angle(heading.to_angle() + self.angle) * config.wander_circle_radius;
+        circle_center + offset
+    }
+}
+
+type IdleFilter = (Without<Chasing>, Without<Pursue>);
+
+/// Boids busy seeking a target or pursuing a quarry don't wander off
+fn wander_behaviour(
+    mut q_boids: Populated<(BoidsQuery, &mut Wander), IdleFilter>,
+    config: Res<SimulationConfig>,
+    time: Res<Time>,
+) {
+    let max_step = config.wander_jitter * time.delta_secs();
+
+    for (mut boid, mut wander) in q_boids.iter_mut() {
+        let seed = config.rng_seed.wrapping_add(boid.entity.to_bits());
+        let jitter = wander
+            .rng
+            .get_or_insert_with(|| StdRng::seed_from_u64(seed))
+            .gen_range(-1.0..=1.0);
+        wander.angle += jitter * max_step;
+
+        let Some(heading) = boid.vel.xy().try_normalize() else {
+            continue;
+        };
+
+        let desired_vel =
+            wander.target(heading, &config).normalize_or_zero() * boid.max_speed(&config);
+        let steer = (desired_vel - boid.vel.xy()).normalize_or_zero();
+        boid.steering
+            .submit(TAG, steer * config.wander_strength, PRIORITY);
+    }
+}
@@ -0,0 +1,558 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use bevy::{
+    audio::AudioPlugin,
+    log::LogPlugin,
+    reflect::{serde::TypedReflectSerializer, GetPath},
+    render::{
+        settings::{RenderCreation, WgpuSettings},
+        RenderPlugin,
+    },
+    scene::ron::{self, ser::PrettyConfig},
+    time::TimeUpdateStrategy,
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{
+    boids::{
+        configuration::SimulationRng,
+        containment::WorldBounds,
+        lifecycle::{BoidBorn, BoidDied, DeathCause},
+        obstacles::SpawnObstacle,
+        perception::NeighbourSearch,
+        predator::Predator,
+        targets::{SeekTarget, SpawnTarget},
+        Boid, BoidsPlugin, SimulationConfig, SpawnBoid,
+    },
+    prelude::*,
+};
+
+/// A tunable field of the [`SimulationConfig`], along with the range it's
+/// searched over
+struct Gene {
+    field: &'static str,
+    min: f32,
+    max: f32,
+}
+
+const GENES: [Gene; 8] = [
+    Gene {
+        field: "separation_strength",
+        min: 0.,
+        max: 3.,
+    },
+    Gene {
+        field: "alignment_strength",
+        min: 0.,
+        max: 3.,
+    },
+    Gene {
+        field: "cohesion_strength",
+        min: 0.,
+        max: 3.,
+    },
+    Gene {
+        field: "seek_strength",
+        min: 0.,
+        max: 1.,
+    },
+    Gene {
+        field: "wander_strength",
+        min: 0.,
+        max: 1.,
+    },
+    Gene {
+        field: "evade_strength",
+        min: 0.,
+        max: 4.,
+    },
+    Gene {
+        field: "collision_avoidance_strength",
+        min: 0.,
+        max: 4.,
+    },
+    Gene {
+        field: "obstacle_avoidance_strength",
+        min: 0.,
+        max: 4.,
+    },
+];
+
+/// One value per gene, in the order of [`GENES`]
+type Genome = Vec<f32>;
+
+fn default_genome() -> Genome {
+    let mut config = SimulationConfig::default();
+    GENES
+        .iter()
+        .map(|gene| *gene_mut(&mut config, gene))
+        .collect()
+}
+
+fn random_genome(rng: &mut impl Rng) -> Genome {
+    GENES
+        .iter()
+        .map(|gene| rng.gen_range(gene.min..=gene.max))
+        .collect()
+}
+
+fn gene_mut<'a>(config: &'a mut SimulationConfig, gene: &Gene) -> &'a mut f32 {
+    config
+        .path_mut::<f32>(gene.field)
+        .expect("Genes should be f32 fields of the config")
+}
+
+fn genome_config(genome: &Genome) -> SimulationConfig {
+    let mut config = SimulationConfig::default();
+    for (gene, value) in GENES.iter().zip(genome) {
+        *gene_mut(&mut config, gene) = *value;
+    }
+    config
+}
+
+/// What happened during an episode, for the fitness functions to score
+#[derive(Resource, Default, Debug, Clone)]
+pub struct EpisodeStats {
+    /// The number of fixed ticks simulated so far
+    pub ticks: u32,
+    /// The number of prey that took part in the episode, including the ones
+    /// born or respawned along the way
+    pub prey: usize,
+    pub caught: usize,
+    pub starved: usize,
+    /// How many times a boid got to the seek target
+    pub targets_reached: usize,
+    /// The number of ticks boids spent inside an obstacle, summed over the flock
+    pub obstacle_hits: usize,
+}
+
+impl EpisodeStats {
+    pub fn seconds(&self) -> f32 {
+        self.ticks as f32 * Time::<Fixed>::default().timestep().as_secs_f32()
+    }
+}
+
+/// Scores an episode, the higher the better
+pub trait Fitness {
+    fn score(&self, stats: &EpisodeStats) -> f32;
+}
+
+/// The share of the flock that survived the predators
+pub struct Survival;
+
+impl Fitness for Survival {
+    fn score(&self, stats: &EpisodeStats) -> f32 {
+        1. - stats.caught as f32 / stats.prey.max(1) as f32
+    }
+}
+
+/// How often the flock gets to the seek target, per boid and per minute
+pub struct TargetReach;
+
+impl Fitness for TargetReach {
+    fn score(&self, stats: &EpisodeStats) -> f32 {
+        let minutes = (stats.seconds() / 60.).max(f32::EPSILON);
+        stats.targets_reached as f32 / stats.prey.max(1) as f32 / minutes
+    }
+}
+
+/// The share of the time boids spent clear of the obstacles
+pub struct ObstacleClearance;
+
+impl Fitness for ObstacleClearance {
+    fn score(&self, stats: &EpisodeStats) -> f32 {
+        let boid_ticks = (stats.prey * stats.ticks as usize).max(1);
+        1. - stats.obstacle_hits as f32 / boid_ticks as f32
+    }
+}
+
+/// The sum of several fitness functions
+pub struct Combined(pub Vec<Box<dyn Fitness>>);
+
+impl Fitness for Combined {
+    fn score(&self, stats: &EpisodeStats) -> f32 {
+        self.0.iter().map(|fitness| fitness.score(stats)).sum()
+    }
+}
+
+fn parse_fitness(names: &str) -> Result<Box<dyn Fitness>, String> {
+    let functions = names
+        .split(',')
+        .map(|name| -> Result<Box<dyn Fitness>, String> {
+            match name.trim() {
+                "survival" => Ok(Box::new(Survival)),
+                "targets" => Ok(Box::new(TargetReach)),
+                "obstacles" => Ok(Box::new(ObstacleClearance)),
+                other => Err(format!(
+                    "Unknown fitness function `{other}`, expected survival, targets or obstacles"
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Box::new(Combined(functions)))
+}
+
+/// How the evolution runs, as read from the command line
+pub struct EvolutionSettings {
+    pub generations: usize,
+    pub population: usize,
+    /// How many of the best candidates make it to the next generation untouched
+    pub elite: usize,
+    /// How many episodes every candidate is scored over
+    pub episodes: usize,
+    /// The simulated length of an episode, in seconds
+    pub episode_secs: f32,
+    pub prey: usize,
+    pub predators: usize,
+    /// The chance of each gene being mutated
+    pub mutation_rate: f32,
+    /// How much a mutation moves a gene, as a fraction of its range
+    pub mutation_strength: f32,
+    pub seed: u64,
+    /// Where the best configs get written
+    pub output: PathBuf,
+    pub fitness: Box<dyn Fitness>,
+}
+
+impl Default for EvolutionSettings {
+    fn default() -> Self {
+        Self {
+            generations: 10,
+            population: 16,
+            elite: 2,
+            episodes: 1,
+            episode_secs: 30.,
+            prey: 60,
+            predators: 2,
+            mutation_rate: 0.2,
+            mutation_strength: 0.15,
+            seed: 0,
+            output: PathBuf::from("evolution"),
+            fitness: Box::new(Combined(vec![
+                Box::new(Survival),
+                Box::new(TargetReach),
+                Box::new(ObstacleClearance),
+            ])),
+        }
+    }
+}
+
+impl EvolutionSettings {
+    /// The settings of the evolution mode if `--evolve` was passed, leaving the
+    /// rest of the flags to their defaults. Without it, the arguments are left
+    /// alone for the interactive simulation
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>, String> {
+        let args = args.into_iter().skip(1).collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--evolve") {
+            return Ok(None);
+        }
+        let mut args = args.into_iter().filter(|arg| arg != "--evolve");
+        let mut settings = Self::default();
+
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for `{arg}`"))?;
+            match arg.as_str() {
+                "--generations" => settings.generations = parse(&arg, &value)?,
+                "--population" => settings.population = parse(&arg, &value)?,
+                "--elite" => settings.elite = parse(&arg, &value)?,
+                "--episodes" => settings.episodes = parse(&arg, &value)?,
+                "--episode-secs" => settings.episode_secs = parse(&arg, &value)?,
+                "--prey" => settings.prey = parse(&arg, &value)?,
+                "--predators" => settings.predators = parse(&arg, &value)?,
+                "--mutation-rate" => settings.mutation_rate = parse(&arg, &value)?,
+                "--mutation-strength" => settings.mutation_strength = parse(&arg, &value)?,
+                "--seed" => settings.seed = parse(&arg, &value)?,
+                "--output" => settings.output = PathBuf::from(value),
+                "--fitness" => settings.fitness = parse_fitness(&value)?,
+                _ => return Err(format!("Unknown argument `{arg}`")),
+            }
+        }
+
+        if settings.population < 2 || settings.episodes == 0 {
+            return Err("The evolution needs at least 2 candidates and 1 episode".into());
+        }
+        settings.elite = settings.elite.min(settings.population);
+        Ok(Some(settings))
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value `{value}` for `{arg}`"))
+}
+
+/// The scene of an episode, laid out the same way for every candidate
+#[derive(Resource, Clone, Copy)]
+struct EpisodeSetup {
+    prey: usize,
+    predators: usize,
+}
+
+fn setup_episode(
+    setup: Res<EpisodeSetup>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<SimulationRng>,
+    mut commands: Commands,
+) {
+    let rng = rng.rng();
+    let side = (setup.prey as f32).sqrt().ceil() as usize;
+    for i in 0..setup.prey {
+        let (x, y) = (i % side, i / side);
+        commands.trigger(SpawnBoid {
+            loc: Vec2::new(x as f32 - side as f32 / 2., y as f32 - side as f32 / 2.) * 50.,
+            angle: rng.gen_range(0. ..std::f32::consts::TAU),
+            ..Default::default()
+        });
+    }
+
+    for i in 0..setup.predators {
+        let angle = i as f32 * std::f32::consts::TAU / setup.predators as f32;
+        commands.trigger(SpawnBoid {
+            loc: Vec2::from_angle(angle) * 800.,
+            predator: true,
+            ..Default::default()
+        });
+    }
+
+    for pos in [(-600., 300.), (650., -250.), (0., -550.)] {
+        commands.trigger(SpawnObstacle::circle(60.).with_pos(pos.into()));
+    }
+    commands.trigger(SpawnTarget::<SeekTarget>::new(bounds.random_point(rng)));
+}
+
+fn count_ticks(mut stats: ResMut<EpisodeStats>) {
+    stats.ticks += 1;
+}
+
+fn count_births(
+    trigger: Trigger<BoidBorn>,
+    q_predators: Query<(), With<Predator>>,
+    mut stats: ResMut<EpisodeStats>,
+) {
+    if !q_predators.contains(trigger.boid) {
+        stats.prey += 1;
+    }
+}
+
+fn count_deaths(trigger: Trigger<BoidDied>, mut stats: ResMut<EpisodeStats>) {
+    match trigger.cause {
+        DeathCause::Caught => stats.caught += 1,
+        DeathCause::Starvation => stats.starved += 1,
+        DeathCause::OldAge => {}
+    }
+}
+
+/// Moves the seek target somewhere else every time a boid gets to it
+fn move_reached_targets(
+    mut q_targets: Query<(&mut Transform, &CollidingEntities), With<SeekTarget>>,
+    q_prey: Query<(), (With<Boid>, Without<Predator>)>,
+    bounds: Res<WorldBounds>,
+    mut rng: ResMut<SimulationRng>,
+    mut stats: ResMut<EpisodeStats>,
+) {
+    for (mut transform, colliding) in q_targets.iter_mut() {
+        if colliding.iter().any(|ent| q_prey.contains(*ent)) {
+            stats.targets_reached += 1;
+            let pos = bounds.random_point(rng.rng());
+            transform.translation = pos.extend(transform.translation.z);
+        }
+    }
+}
+
+fn count_obstacle_hits(
+    q_prey: Query<&Transform, (With<Boid>, Without<Predator>)>,
+    spatial_query: SpatialQuery,
+    mut stats: ResMut<EpisodeStats>,
+) {
+    let filter = SpatialQueryFilter::from_mask(GameCollisionLayer::Obstacles);
+    stats.obstacle_hits += q_prey
+        .iter()
+        .filter(|transform| {
+            !spatial_query
+                .point_intersections(transform.translation.truncate(), &filter)
+                .is_empty()
+        })
+        .count();
+}
+
+/// Simulates one episode without any window or rendering, as fast as possible
+fn run_episode(config: SimulationConfig, setup: EpisodeSetup, ticks: u32) -> EpisodeStats {
+    let seed = config.rng_seed;
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: RenderCreation::Automatic(WgpuSettings {
+                    backends: None,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<AudioPlugin>()
+            .disable::<LogPlugin>(),
+        PhysicsPlugins::default(),
+        BoidsPlugin {
+            neighbour_search: NeighbourSearch::Grid,
+        },
+    ))
+    .insert_resource(config)
+    .insert_resource(SimulationRng::new(seed))
+    .insert_resource(setup)
+    .init_resource::<EpisodeStats>()
+    // Every update simulates exactly one fixed tick
+    .insert_resource(TimeUpdateStrategy::ManualDuration(
+        Time::<Fixed>::default().timestep(),
+    ))
+    .add_systems(Startup, setup_episode)
+    // Once the physics have caught up with the tick, so that the collisions are
+    // fresh and a moved target doesn't get synced back in place
+    .add_systems(
+        FixedPostUpdate,
+        (count_ticks, move_reached_targets, count_obstacle_hits).after(PhysicsSet::Sync),
+    )
+    .add_observer(count_births)
+    .add_observer(count_deaths);
+
+    app.finish();
+    app.cleanup();
+    while app.world().resource::<EpisodeStats>().ticks < ticks {
+        app.update();
+    }
+    app.world().resource::<EpisodeStats>().clone()
+}
+
+/// Picks the best of a few random candidates
+fn tournament<'a>(scored: &'a [(Genome, f32)], rng: &mut impl Rng) -> &'a Genome {
+    (0..3)
+        .map(|_| &scored[rng.gen_range(0..scored.len())])
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(genome, _)| genome)
+        .expect("There should be candidates to pick from")
+}
+
+/// Takes every gene from either parent, then nudges some of them
+fn offspring(a: &Genome, b: &Genome, settings: &EvolutionSettings, rng: &mut impl Rng) -> Genome {
+    GENES
+        .iter()
+        .zip(a.iter().zip(b))
+        .map(|(gene, (a, b))| {
+            let value = if rng.gen_bool(0.5) { *a } else { *b };
+            if rng.gen::<f32>() < settings.mutation_rate {
+                let range = gene.max - gene.min;
+                let nudge = rng.gen_range(-1. ..=1.) * settings.mutation_strength * range;
+                (value + nudge).clamp(gene.min, gene.max)
+            } else {
+                value
+            }
+        })
+        .collect()
+}
+
+fn write_config(
+    path: &PathBuf,
+    config: &SimulationConfig,
+    registry: &AppTypeRegistry,
+) -> Result<(), String> {
+    let registry = registry.read();
+    let serializer = TypedReflectSerializer::new(config, &registry);
+    let ron = ron::ser::to_string_pretty(&serializer, PrettyConfig::default())
+        .map_err(|err| format!("Couldn't serialize the config: {err}"))?;
+    fs::write(path, ron).map_err(|err| format!("Couldn't write {}: {err}", path.display()))
+}
+
+/// Evolves the steering weights over the generations, writing the best config of
+/// every generation, and the best one overall, to the output directory
+pub fn evolve(settings: EvolutionSettings) -> Result<(), String> {
+    fs::create_dir_all(&settings.output)
+        .map_err(|err| format!("Couldn't create {}: {err}", settings.output.display()))?;
+    let registry = AppTypeRegistry::default();
+    registry.write().register::<SimulationConfig>();
+
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let setup = EpisodeSetup {
+        prey: settings.prey,
+        predators: settings.predators,
+    };
+    let ticks = (settings.episode_secs / Time::<Fixed>::default().timestep().as_secs_f32()) as u32;
+
+    // The hand-tuned defaults compete too, so the evolution can only improve on them
+    let mut population = vec![default_genome()];
+    population.extend((1..settings.population).map(|_| random_genome(&mut rng)));
+    let mut best: Option<(Genome, f32)> = None;
+
+    for generation in 0..settings.generations {
+        // Every candidate faces the same episodes, so that luck doesn't decide
+        let seeds = (0..settings.episodes)
+            .map(|_| rng.gen())
+            .collect::<Vec<u64>>();
+        let mut scored = population
+            .into_iter()
+            .map(|genome| {
+                let score = seeds
+                    .iter()
+                    .map(|seed| {
+                        let config = SimulationConfig {
+                            rng_seed: *seed,
+                            respawn_caught_prey: false,
+                            ..genome_config(&genome)
+                        };
+                        settings.fitness.score(&run_episode(config, setup, ticks))
+                    })
+                    .sum::<f32>()
+                    / seeds.len() as f32;
+                (genome, score)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        let (generation_best, score) = &scored[0];
+        println!("Generation {generation}: best score {score:.3}");
+        write_config(
+            &settings
+                .output
+                .join(format!("generation_{generation:03}.ron")),
+            &genome_config(generation_best),
+            &registry,
+        )?;
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_score)| score > best_score)
+        {
+            best = Some((generation_best.clone(), *score));
+            write_config(
+                &settings.output.join("best.ron"),
+                &genome_config(generation_best),
+                &registry,
+            )?;
+        }
+
+        population = scored
+            .iter()
+            .take(settings.elite)
+            .map(|(genome, _)| genome.clone())
+            .collect();
+        while population.len() < settings.population {
+            let (a, b) = (tournament(&scored, &mut rng), tournament(&scored, &mut rng));
+            population.push(offspring(a, b, &settings, &mut rng));
+        }
+    }
+
+    if let Some((_, score)) = best {
+        println!(
+            "Best score {score:.3}, written to {}",
+            settings.output.join("best.ron").display()
+        );
+    }
+    Ok(())
+}
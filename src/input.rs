@@ -1,11 +1,23 @@
-use bevy::input::{
-    gestures::{DoubleTapGesture, PanGesture, PinchGesture, RotationGesture},
-    mouse::MouseWheel,
-};
+use bevy::input::{gestures::PinchGesture, mouse::MouseWheel};
 use bevy_inspector_egui::{quick::ResourceInspectorPlugin, InspectorOptions};
 
 use crate::{
-    boids::targets::{SeekTarget, SpawnTarget},
+    boids::{
+        cohesion::Cohesion,
+        containment::{ContainmentMode, WorldBounds},
+        flow_field::FlowField,
+        leader::{Leader, SetLeader},
+        obstacles::SpawnObstacle,
+        params::BoidParams,
+        path_following::{FollowPath, Path},
+        predator::Predator,
+        pursuit::{Evade, Pursue},
+        seek::{Arrive, Seek},
+        separation::Separation,
+        steering::{CombinationStrategy, SteeringArbitration, SteeringBehaviour},
+        targets::{FleeTarget, FoodSource, SeekTarget, SpawnTarget},
+        Boid, SimulationConfig, SpecialBoid,
+    },
     prelude::*,
     MainCamera,
 };
@@ -16,7 +28,16 @@ impl Plugin for SimulationInputPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            (handle_mouse_inputs, handle_scrolling, handle_gestures),
+            (
+                handle_mouse_inputs,
+                handle_keyboard_inputs,
+                handle_flow_field_inputs,
+                handle_bounds_inputs,
+                handle_steering_inputs,
+                handle_params_inputs,
+                handle_scrolling,
+                handle_gestures,
+            ),
         )
         .insert_resource(InputConfig::default())
         .register_type::<InputConfig>()
@@ -48,17 +69,226 @@ fn handle_mouse_inputs(
     mut commands: Commands,
 ) {
     let (camera, camera_global_tr) = *q_camera;
-    if mouse.just_pressed(MouseButton::Left) && keyboad.pressed(KeyCode::ShiftLeft) {
-        let Some(cursor_pos) = window.cursor_position() else {
+    if !mouse.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        return;
+    }
+
+    let Some(cursor_pos) = window.cursor_position() else {
+        return;
+    };
+    let world_pos = camera
+        .viewport_to_world_2d(camera_global_tr, cursor_pos)
+        .expect("Cursor should convert");
+
+    if mouse.just_pressed(MouseButton::Right) {
+        commands.trigger(SpawnTarget::<FoodSource>::new(world_pos));
+    } else if keyboad.pressed(KeyCode::ShiftLeft) {
+        commands.trigger(SpawnTarget::<SeekTarget>::new(world_pos));
+    } else if keyboad.pressed(KeyCode::ControlLeft) {
+        commands.trigger(SpawnTarget::<FleeTarget>::new(world_pos));
+    } else if keyboad.pressed(KeyCode::AltLeft) {
+        commands.trigger(SpawnObstacle::circle(40.).with_pos(world_pos));
+    }
+}
+
+/// The red boid, which may not be around at every moment
+type SpecialBoidSingle<'w, D> = Option<Single<'w, D, With<SpecialBoid>>>;
+
+type FlockFilter = (With<Boid>, Without<SpecialBoid>, Without<Predator>);
+
+#[allow(clippy::too_many_arguments)]
+fn handle_keyboard_inputs(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    q_seekers: Query<(Entity, Has<Arrive>), With<Seek>>,
+    q_special: SpecialBoidSingle<(Entity, &Transform, Has<Pursue>)>,
+    q_boids: Query<(Entity, &Transform), FlockFilter>,
+    q_all_boids: Query<(Entity, Has<FollowPath>), With<Boid>>,
+    q_paths: Query<Entity, With<Path>>,
+    q_leaders: Query<Entity, With<Leader>>,
+    mut commands: Commands,
+) {
+    // Toggle the special boid leading the flock
+    if keyboard.just_pressed(KeyCode::KeyL) {
+        if let Some(special) = q_special.as_ref() {
+            let new_leader = (!q_leaders.contains(special.0)).then_some(special.0);
+            commands.trigger(SetLeader(new_leader));
+        }
+    }
+
+    // Toggle following the first path around
+    if keyboard.just_pressed(KeyCode::KeyF) {
+        if let Some(path) = q_paths.iter().next() {
+            for (ent, following) in q_all_boids.iter() {
+                if following {
+                    commands.entity(ent).remove::<FollowPath>();
+                } else {
+                    commands.entity(ent).insert(FollowPath(path));
+                }
+            }
+        }
+    }
+
+    // Toggle between circling the seek targets and parking at them
+    if keyboard.just_pressed(KeyCode::KeyA) {
+        for (ent, arriving) in q_seekers.iter() {
+            if arriving {
+                commands.entity(ent).remove::<Arrive>();
+            } else {
+                commands.entity(ent).insert(Arrive);
+            }
+        }
+    }
+
+    // Toggle the hunt: the special boid chases its closest flockmate,
+    // while everyone else tries to get away from it
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        let Some(special) = q_special else {
             return;
         };
-        let world_pos = camera
-            .viewport_to_world_2d(camera_global_tr, cursor_pos)
-            .expect("Cursor should convert");
-        commands.trigger(SpawnTarget::<SeekTarget>::new(world_pos));
+        let (hunter, hunter_tr, hunting) = *special;
+
+        if hunting {
+            commands.entity(hunter).remove::<Pursue>();
+            for (ent, _) in q_boids.iter() {
+                commands.entity(ent).remove::<Evade>();
+            }
+            return;
+        }
+
+        let closest = q_boids.iter().min_by(|(_, a), (_, b)| {
+            let dist_a = a.translation.distance_squared(hunter_tr.translation);
+            let dist_b = b.translation.distance_squared(hunter_tr.translation);
+            dist_a.total_cmp(&dist_b)
+        });
+        if let Some((quarry, _)) = closest {
+            commands.entity(hunter).insert(Pursue(quarry));
+        }
+        for (ent, _) in q_boids.iter() {
+            commands.entity(ent).insert(Evade(hunter));
+        }
     }
 }
 
+const FLOW_FIELD_CELL_SIZE: f32 = 50.;
+
+/// Cycles the flow field between none, perlin noise and obstacle repulsion
+fn handle_flow_field_inputs(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    spatial_query: SpatialQuery,
+    config: Res<SimulationConfig>,
+    bounds: Res<WorldBounds>,
+    mut field_kind: Local<usize>,
+    mut commands: Commands,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyW) {
+        return;
+    }
+
+    // Cover the whole world with the field
+    let origin = -bounds.half_extents();
+    let size = (bounds.half_extents() * 2. / FLOW_FIELD_CELL_SIZE)
+        .ceil()
+        .as_uvec2();
+
+    *field_kind = (*field_kind + 1) % 3;
+    match *field_kind {
+        1 => commands.insert_resource(FlowField::perlin(
+            origin,
+            FLOW_FIELD_CELL_SIZE,
+            size,
+            0.15,
+            config.rng_seed,
+        )),
+        2 => commands.insert_resource(FlowField::from_obstacles(
+            &spatial_query,
+            origin,
+            FLOW_FIELD_CELL_SIZE,
+            size,
+            200.,
+        )),
+        _ => commands.remove_resource::<FlowField>(),
+    }
+}
+
+/// Cycles the shape of the world between rectangular, circular and toroidal
+fn handle_bounds_inputs(keyboard: Res<ButtonInput<KeyCode>>, mut bounds: ResMut<WorldBounds>) {
+    if keyboard.just_pressed(KeyCode::KeyB) {
+        bounds.mode = match bounds.mode {
+            ContainmentMode::Rectangle => ContainmentMode::Circle,
+            ContainmentMode::Circle => ContainmentMode::Toroidal,
+            ContainmentMode::Toroidal => ContainmentMode::Rectangle,
+        };
+    }
+}
+
+/// Cycles the way the special boid combines its steering forces, so it can be
+/// compared against the rest of the flock
+fn handle_steering_inputs(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    q_special: SpecialBoidSingle<(Entity, Option<&mut SteeringArbitration>)>,
+    mut commands: Commands,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyS) {
+        return;
+    }
+    let Some(special) = q_special else {
+        return;
+    };
+    let (ent, arbitration) = special.into_inner();
+
+    let strategy = match arbitration.as_ref().and_then(|a| a.strategy) {
+        None => Some(CombinationStrategy::WeightedTruncatedSum),
+        Some(CombinationStrategy::WeightedTruncatedSum) => {
+            Some(CombinationStrategy::PrioritizedTruncatedSum)
+        }
+        Some(CombinationStrategy::PrioritizedTruncatedSum) => {
+            Some(CombinationStrategy::PrioritizedDithering)
+        }
+        Some(CombinationStrategy::PrioritizedDithering) => None,
+    };
+    info!("Special boid steering strategy: {strategy:?}");
+    // Only the strategy changes, the boid keeps its own priorities
+    match arbitration {
+        Some(mut arbitration) => arbitration.strategy = strategy,
+        None => {
+            commands.entity(ent).insert(SteeringArbitration {
+                strategy,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+fn handle_params_inputs(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    q_special: SpecialBoidSingle<(Entity, Has<BoidParams>)>,
+    mut commands: Commands,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyH) {
+        return;
+    }
+    let Some(special) = q_special else {
+        return;
+    };
+    let (ent, has_params) = *special;
+
+    if has_params {
+        info!("Special boid is back to the global settings");
+        commands.entity(ent).remove::<BoidParams>();
+        return;
+    }
+
+    // A nervous individual: quick and twitchy, keeping its flockmates at bay
+    info!("Special boid is nervous");
+    commands.entity(ent).insert(
+        BoidParams::default()
+            .with_max_speed(220.)
+            .with_max_force(0.05)
+            .with_weight(Separation::TAG, 2.5)
+            .with_weight(Cohesion::TAG, 0.5),
+    );
+}
+
 // Getsures for MacOS
 fn handle_gestures(
     mut evr_gesture_pinch: EventReader<PinchGesture>,
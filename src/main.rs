@@ -1,5 +1,12 @@
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
-use boids::{obstacles::SpawnObstacle, BoidsPlugin, SpawnBoid};
+use boids::{
+    obstacles::SpawnObstacle,
+    params::BoidParams,
+    path_following::Path,
+    species::{Species, SpeciesInfo, SpeciesInteraction, SpeciesRegistry},
+    BoidsPlugin, SpawnBoid,
+};
+use evolution::EvolutionSettings;
 use i_cant_believe_its_not_bsn::*;
 use input::SimulationInputPlugin;
 
@@ -7,20 +14,23 @@ use crate::prelude::*;
 
 mod prelude;
 
+#[allow(dead_code)]
 mod editor;
 
 mod input;
 
 mod boids;
 
+mod evolution;
+
 #[cfg(test)]
 mod tests;
 
 #[derive(Component)]
 pub struct MainCamera;
 
-fn setup(mut commands: Commands) {
-    commands.spawn((Camera2d::default(), MainCamera));
+fn setup(mut commands: Commands, mut registry: ResMut<SpeciesRegistry>) {
+    commands.spawn((Camera2d, MainCamera));
 
     let x_count = 15;
     let y_count = 15;
@@ -36,13 +46,50 @@ fn setup(mut commands: Commands) {
                 loc,
                 angle,
                 special: x == 0 && y == 0,
+                ..Default::default()
             };
             commands.trigger(trigger);
         }
     }
 
-    let bounding_box_color = Color::srgb(0.1, 0.1, 0.1);
-    commands.trigger(SpawnObstacle::rectangle(2400., 1800.).with_color(bounding_box_color));
+    // A second, smaller and quicker flock, too bold to care about the first one
+    let amber = registry.register(
+        SpeciesInfo::new("Amber", Color::srgb_u8(230, 160, 30))
+            .with_scale(8.)
+            .with_params(BoidParams::default().with_max_speed(170.)),
+    );
+    registry.set_interaction(amber, Species::default(), SpeciesInteraction::Ignore);
+    for x in 0..8 {
+        for y in 0..8 {
+            commands.trigger(SpawnBoid {
+                loc: Vec2::new(-900. + x as f32 * 45., 200. + y as f32 * 45.),
+                angle: rand::thread_rng().gen_range((0.)..std::f32::consts::TAU),
+                species: amber,
+                ..Default::default()
+            });
+        }
+    }
+
+    for (x, y) in [(-800., -600.), (800., 600.)] {
+        commands.trigger(SpawnBoid {
+            loc: Vec2::new(x, y),
+            predator: true,
+            ..Default::default()
+        });
+    }
+
+    let path_points = (0..8)
+        .map(|i| Vec2::from_angle(i as f32 * std::f32::consts::TAU / 8.) * 600.)
+        .collect();
+    commands.spawn((
+        Name::new("Demo Path"),
+        Path::new(path_points).looped().with_radius(40.),
+    ));
+
+    // The world edges are handled by containment, these are just something to fly around
+    for pos in [(-600., 300.), (650., -250.), (0., -550.)] {
+        commands.trigger(SpawnObstacle::circle(60.).with_pos(pos.into()));
+    }
 }
 
 #[derive(Component)]
@@ -85,11 +132,26 @@ fn fps_system(mut commands: Commands, diagnostics: Res<DiagnosticsStore>) {
 pub struct FpsRoot;
 
 fn main() {
+    match EvolutionSettings::from_args(std::env::args()) {
+        Ok(Some(settings)) => {
+            if let Err(err) = evolution::evolve(settings) {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
+        }
+    }
+
     App::new()
         .add_plugins((
             DefaultPlugins,
             PhysicsPlugins::default(),
-            FrameTimeDiagnosticsPlugin::default(),
+            FrameTimeDiagnosticsPlugin,
             // EditorPlugin,
             SimulationInputPlugin,
             // WorldInspectorPlugin::new(),
@@ -10,4 +10,5 @@ pub enum GameCollisionLayer {
     Boids,
     Targets,
     Obstacles,
+    Predators,
 }
@@ -1 +1,446 @@
+mod steering {
+    use rand::{rngs::StdRng, SeedableRng};
 
+    use crate::{
+        boids::steering::{CombinationStrategy, SteeringForce, SteeringTag},
+        prelude::*,
+    };
+
+    fn forces(forces: &[Vec2]) -> Vec<SteeringForce> {
+        forces
+            .iter()
+            .enumerate()
+            .map(|(i, force)| SteeringForce {
+                tag: SteeringTag("Test"),
+                force: *force,
+                priority: -(i as i32),
+            })
+            .collect()
+    }
+
+    fn combine(strategy: CombinationStrategy, submitted: &[Vec2], max_force: f32) -> Vec<Vec2> {
+        let mut rng = StdRng::seed_from_u64(0);
+        strategy.combine(&forces(submitted), max_force, 1., &mut rng)
+    }
+
+    #[test]
+    fn weighted_truncated_sum_keeps_forces_within_budget() {
+        let submitted = [Vec2::new(1., 0.), Vec2::new(0., 1.)];
+        let applied = combine(CombinationStrategy::WeightedTruncatedSum, &submitted, 3.);
+        assert_eq!(applied, submitted);
+    }
+
+    #[test]
+    fn weighted_truncated_sum_scales_everything_down_to_budget() {
+        let submitted = [Vec2::new(3., 0.), Vec2::new(0., 4.)];
+        let applied = combine(CombinationStrategy::WeightedTruncatedSum, &submitted, 2.5);
+        let total = applied.iter().sum::<Vec2>();
+        assert!((total.length() - 2.5).abs() < 1e-5);
+        // The proportions between the behaviours are kept
+        assert!((applied[0].x / applied[1].y - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn prioritized_truncated_sum_serves_highest_priority_first() {
+        let submitted = [Vec2::new(2., 0.), Vec2::new(2., 0.), Vec2::new(2., 0.)];
+        let applied = combine(CombinationStrategy::PrioritizedTruncatedSum, &submitted, 3.);
+        assert_eq!(applied, [Vec2::new(2., 0.), Vec2::new(1., 0.), Vec2::ZERO]);
+    }
+
+    #[test]
+    fn prioritized_dithering_picks_a_single_force() {
+        let submitted = [Vec2::ZERO, Vec2::new(0., 5.), Vec2::new(1., 0.)];
+        let applied = combine(CombinationStrategy::PrioritizedDithering, &submitted, 3.);
+        // Forces with nothing to say are skipped, and the picked one is truncated
+        assert_eq!(applied, [Vec2::ZERO, Vec2::new(0., 3.), Vec2::ZERO]);
+    }
+}
+
+mod perception {
+    use crate::{boids::perception::NeighbourGrid, prelude::*};
+
+    fn grid(cell_size: f32, positions: &[Vec2]) -> NeighbourGrid {
+        let mut grid = NeighbourGrid::default();
+        grid.rebuild(
+            cell_size,
+            positions
+                .iter()
+                .enumerate()
+                .map(|(i, pos)| (Entity::from_raw(i as u32), *pos)),
+        );
+        grid
+    }
+
+    fn sorted(found: impl Iterator<Item = (Entity, Vec2)>) -> Vec<u32> {
+        let mut indices = found.map(|(ent, _)| ent.index()).collect::<Vec<_>>();
+        indices.sort();
+        indices
+    }
+
+    #[test]
+    fn around_finds_everything_within_a_cell_size() {
+        let grid = grid(
+            10.,
+            &[
+                Vec2::new(5., 5.),
+                Vec2::new(14., 5.),
+                Vec2::new(-4., -4.),
+                Vec2::new(25., 5.),
+                Vec2::new(5., -15.),
+            ],
+        );
+        assert_eq!(sorted(grid.around(Vec2::new(5., 5.))), [0, 1, 2]);
+    }
+
+    #[test]
+    fn around_covers_negative_cells() {
+        let grid = grid(10., &[Vec2::new(-0.5, -0.5), Vec2::new(-19., -19.)]);
+        assert_eq!(sorted(grid.around(Vec2::new(-1., -1.))), [0, 1]);
+    }
+
+    #[test]
+    fn around_at_least_widens_until_enough_are_found() {
+        let grid = grid(
+            10.,
+            &[
+                Vec2::new(5., 5.),
+                Vec2::new(35., 5.),
+                Vec2::new(45., 5.),
+                Vec2::new(55., 5.),
+                Vec2::new(95., 5.),
+            ],
+        );
+        // The rings widen until a second boid turns up three cells away, then
+        // one more ring catches the ones just across the border
+        let found = grid.around_at_least(Vec2::new(5., 5.), 1, 8);
+        assert_eq!(sorted(found.into_iter()), [0, 1, 2]);
+    }
+
+    #[test]
+    fn around_at_least_gives_up_after_max_rings() {
+        let grid = grid(10., &[Vec2::new(5., 5.), Vec2::new(95., 5.)]);
+        let found = grid.around_at_least(Vec2::new(5., 5.), 5, 3);
+        assert_eq!(sorted(found.into_iter()), [0]);
+    }
+}
+
+mod falloff {
+    use bevy::math::curve::SampleAutoCurve;
+
+    use crate::{
+        boids::{
+            configuration::SimulationConfig,
+            falloff::Falloff,
+            separation::Separation,
+            steering::{BoidState, SteeringBehaviour},
+        },
+        prelude::*,
+    };
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn linear_and_smoothstep_fade_out_at_the_edge() {
+        for falloff in [Falloff::Linear, Falloff::Smoothstep] {
+            assert!(approx(falloff.weight(0., 10.), 1.));
+            assert!(approx(falloff.weight(10., 10.), 0.));
+            assert!(approx(falloff.weight(25., 10.), 0.));
+        }
+        assert!(approx(Falloff::Linear.weight(2.5, 10.), 0.75));
+        assert!(approx(Falloff::Smoothstep.weight(5., 10.), 0.5));
+    }
+
+    #[test]
+    fn inverse_falloffs_are_capped_up_close() {
+        assert!(approx(Falloff::Inverse.weight(10., 10.), 1.));
+        assert!(approx(Falloff::Inverse.weight(5., 10.), 2.));
+        assert!(approx(Falloff::Inverse.weight(0., 10.), 10.));
+        assert!(approx(Falloff::InverseSquare.weight(5., 10.), 4.));
+        assert!(approx(Falloff::InverseSquare.weight(0., 10.), 100.));
+    }
+
+    #[test]
+    fn custom_falloff_samples_the_relative_distance() {
+        let curve = SampleAutoCurve::new(Interval::UNIT, [0., 1.]).unwrap();
+        let falloff = Falloff::Custom(curve);
+        assert!(approx(falloff.weight(2.5, 10.), 0.25));
+        assert!(approx(falloff.weight(40., 10.), 1.));
+    }
+
+    fn boid(x: f32, y: f32) -> BoidState {
+        BoidState {
+            pos: Vec2::new(x, y),
+            vel: Vec2::ZERO,
+            max_speed: 10.,
+        }
+    }
+
+    #[test]
+    fn separation_is_capped_to_unit_length() {
+        let config = SimulationConfig::default();
+        let neighbours = [boid(1., 0.), boid(0., 1.), boid(2., 2.)];
+        let steer = Separation::steer(&boid(0., 0.), &neighbours, &config).unwrap();
+        assert!(approx(steer.length(), 1.));
+        assert!(steer.x < 0. && steer.y < 0.);
+    }
+
+    #[test]
+    fn separation_has_nothing_to_say_when_pushes_cancel_out() {
+        let config = SimulationConfig::default();
+        let neighbours = [boid(-10., 0.), boid(10., 0.)];
+        assert_eq!(Separation::steer(&boid(0., 0.), &neighbours, &config), None);
+        assert_eq!(Separation::steer(&boid(0., 0.), &[], &config), None);
+    }
+}
+
+mod evolution {
+    use std::path::PathBuf;
+
+    use crate::evolution::{EpisodeStats, EvolutionSettings};
+
+    fn from_args(args: &[&str]) -> Result<Option<EvolutionSettings>, String> {
+        let args = ["bevy-boids"].iter().chain(args).map(|arg| arg.to_string());
+        EvolutionSettings::from_args(args)
+    }
+
+    #[test]
+    fn other_arguments_are_left_alone_without_evolve() {
+        assert!(matches!(from_args(&[]), Ok(None)));
+        assert!(matches!(from_args(&["--whatever", "-x"]), Ok(None)));
+        assert!(matches!(from_args(&["--population", "1"]), Ok(None)));
+    }
+
+    #[test]
+    fn flags_override_the_defaults() {
+        let settings = from_args(&[
+            "--population",
+            "8",
+            "--evolve",
+            "--episode-secs",
+            "12.5",
+            "--output",
+            "out",
+            "--elite",
+            "20",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(settings.population, 8);
+        assert_eq!(settings.episode_secs, 12.5);
+        assert_eq!(settings.output, PathBuf::from("out"));
+        // There can't be more elites than candidates
+        assert_eq!(settings.elite, 8);
+        assert_eq!(
+            settings.generations,
+            EvolutionSettings::default().generations
+        );
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert_eq!(
+            from_args(&["--evolve", "--bogus", "1"]).err().unwrap(),
+            "Unknown argument `--bogus`"
+        );
+        assert_eq!(
+            from_args(&["--evolve", "--seed"]).err().unwrap(),
+            "Missing value for `--seed`"
+        );
+        assert_eq!(
+            from_args(&["--evolve", "--prey", "many"]).err().unwrap(),
+            "Invalid value `many` for `--prey`"
+        );
+        assert!(from_args(&["--evolve", "--population", "1"]).is_err());
+        assert!(from_args(&["--evolve", "--fitness", "survival,speed"]).is_err());
+    }
+
+    #[test]
+    fn fitness_functions_add_up() {
+        let settings = from_args(&["--evolve", "--fitness", "survival, obstacles"])
+            .unwrap()
+            .unwrap();
+        let stats = EpisodeStats {
+            ticks: 10,
+            prey: 4,
+            caught: 1,
+            obstacle_hits: 20,
+            ..Default::default()
+        };
+        assert!((settings.fitness.score(&stats) - (0.75 + 0.5)).abs() < 1e-5);
+    }
+}
+
+mod pursuit {
+    use crate::{
+        boids::{
+            flee::flee_from,
+            pursuit::{evade_threat, predict_position},
+            steering::BoidState,
+        },
+        prelude::*,
+    };
+
+    fn still(x: f32, y: f32) -> BoidState {
+        BoidState {
+            pos: Vec2::new(x, y),
+            vel: Vec2::ZERO,
+            max_speed: 10.,
+        }
+    }
+
+    #[test]
+    fn predicted_position_is_capped_by_the_horizon() {
+        let quarry_vel = Vec2::new(0., 10.);
+        let near = predict_position(Vec2::ZERO, Vec2::new(20., 0.), quarry_vel, 10., 5.);
+        assert_eq!(near, Vec2::new(20., 20.));
+        let far = predict_position(Vec2::ZERO, Vec2::new(200., 0.), quarry_vel, 10., 5.);
+        assert_eq!(far, Vec2::new(200., 50.));
+    }
+
+    #[test]
+    fn fleeing_grows_more_urgent_up_close() {
+        let boid = still(0., 0.);
+        let threat = Vec2::new(10., 0.);
+        assert_eq!(flee_from(&boid, threat, 10., 10.), None);
+        assert_eq!(flee_from(&boid, threat, 5., 10.), Some(Vec2::new(-0.5, 0.)));
+        assert_eq!(flee_from(&boid, threat, 0., 10.), Some(Vec2::new(-1., 0.)));
+    }
+
+    #[test]
+    fn evading_runs_from_where_the_threat_is_headed() {
+        let threat = BoidState {
+            pos: Vec2::new(10., 0.),
+            vel: Vec2::new(0., -10.),
+            max_speed: 10.,
+        };
+        // The threat cuts across in front of the boid, so the boid backs off
+        // up and away from its path rather than straight away from it
+        let steer = evade_threat(&still(0., 0.), &threat, 20., 1.).unwrap();
+        assert!(steer.x < 0. && steer.y > 0.);
+        assert!((steer.length() - 0.5).abs() < 1e-5);
+        assert_eq!(evade_threat(&still(0., 0.), &threat, 5., 1.), None);
+    }
+}
+
+mod lifecycle {
+    use std::f32::consts::TAU;
+
+    use bevy::{
+        render::{render_resource::Shader, view::RenderLayers},
+        state::app::StatesPlugin,
+        time::TimeUpdateStrategy,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        boids::{
+            configuration::SimulationConfig,
+            lifecycle::BoidBorn,
+            params::BoidParams,
+            perception::{NeighbourSearch, Neighbours},
+            BoidsPlugin, SpawnBoid,
+        },
+        prelude::*,
+    };
+
+    /// The simulation without any window or rendering, ticking once per update
+    fn headless_app(neighbour_search: NeighbourSearch) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StatesPlugin,
+            bevy::input::InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            bevy::scene::ScenePlugin,
+        ))
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<Image>()
+        .init_asset::<Shader>()
+        .register_type::<RenderLayers>()
+        .add_plugins((PhysicsPlugins::default(), BoidsPlugin { neighbour_search }))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(
+            Time::<Fixed>::default().timestep(),
+        ));
+        app.update();
+        app
+    }
+
+    fn spawn_ring(app: &mut App, center: Vec2) {
+        for i in 0..6 {
+            let angle = i as f32 * TAU / 6.;
+            app.world_mut().trigger(SpawnBoid {
+                loc: center + Vec2::from_angle(angle) * 40.,
+                angle,
+                ..Default::default()
+            });
+        }
+    }
+
+    #[derive(Resource, Default)]
+    struct LastBorn(Option<Entity>);
+
+    #[test]
+    fn boids_spawned_mid_simulation_see_their_flockmates() {
+        for search in [NeighbourSearch::Sensors, NeighbourSearch::Grid] {
+            let mut app = headless_app(search);
+            app.init_resource::<LastBorn>().add_observer(
+                |trigger: Trigger<BoidBorn>, mut last: ResMut<LastBorn>| {
+                    last.0 = Some(trigger.boid);
+                },
+            );
+            spawn_ring(&mut app, Vec2::ZERO);
+            for _ in 0..10 {
+                app.update();
+            }
+
+            let veteran = app.world().resource::<LastBorn>().0;
+            app.world_mut().trigger(SpawnBoid {
+                loc: Vec2::ZERO,
+                ..Default::default()
+            });
+            app.world_mut().flush();
+            assert_ne!(app.world().resource::<LastBorn>().0, veteran);
+            let newcomer = app.world().resource::<LastBorn>().0.unwrap();
+            for _ in 0..3 {
+                app.update();
+            }
+            let neighbours = app.world().get::<Neighbours>(newcomer).unwrap();
+            assert!(neighbours.iter().next().is_some(), "{search:?}");
+        }
+    }
+
+    #[test]
+    fn unmutated_limits_keep_following_the_config() {
+        let config = SimulationConfig {
+            mutation_rate: 0.,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = BoidParams::default().mutated(&config, &mut rng);
+        assert_eq!(child.max_speed, None);
+        assert_eq!(child.max_force, None);
+
+        let parent = BoidParams::default().with_max_speed(120.);
+        let child = parent.mutated(&config, &mut rng);
+        assert_eq!(child.max_speed, Some(120.));
+    }
+
+    #[test]
+    fn mutated_limits_are_overridden() {
+        let config = SimulationConfig {
+            mutation_rate: 1.,
+            mutation_strength: 0.5,
+            ..Default::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let child = BoidParams::default().mutated(&config, &mut rng);
+        let max_speed = child.max_speed.unwrap();
+        assert!((config.max_speed * 0.5..=config.max_speed * 1.5).contains(&max_speed));
+        assert!(child.max_force.is_some());
+    }
+}